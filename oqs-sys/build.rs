@@ -68,6 +68,7 @@ fn main() {
         algorithm_feature!("KEM", "bike");
     }
     algorithm_feature!("KEM", "classic_mceliece");
+    algorithm_feature!("KEM", "csidh");
     algorithm_feature!("KEM", "frodokem");
     algorithm_feature!("KEM", "hqc");
     algorithm_feature!("KEM", "kyber");
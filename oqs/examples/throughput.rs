@@ -0,0 +1,174 @@
+//! Measure the throughput of a KEM
+//!
+//! Usage: `cargo run --release --example throughput -- <algorithm> [iterations]`
+//!
+//! `<algorithm>` is a liboqs name such as `SIKE-p434-compressed` or `Kyber512`;
+//! run without arguments to list the enabled ones. `iterations` defaults to 10.
+//!
+//! For algorithms that need [`Kem::init`], such as SIKE, this also compares
+//! [`Kem::rekey`] with rotating keys through `deinit`, `init` and `keypair`.
+//! It also compares decapsulating with fresh output buffers to reusing them
+//! through [`kem::scratch::with_arena`], which needs the `std` feature.
+//! The `decap/raw` row calls liboqs through [`Kem::decapsulate_raw`], showing
+//! the overhead of the checks in the safe API.
+//!
+//! With the `numa` feature, `--node <n>` places the keys and ciphertexts on NUMA
+//! node `n`. Compare local and remote memory by pinning the benchmark to a node:
+//!
+//! ```text
+//! numactl --cpunodebind=0 throughput --node 0 Classic-McEliece-6688128 100
+//! numactl --cpunodebind=0 throughput --node 1 Classic-McEliece-6688128 100
+//! ```
+use std::process;
+use std::time::{Duration, Instant};
+
+use oqs::kem::{self, Kem};
+
+fn usage() -> ! {
+    eprintln!("usage: throughput [--node <n>] <algorithm> [iterations]");
+    eprintln!("enabled algorithms:");
+    for algorithm in kem::all().iter().filter(|alg| alg.is_enabled()) {
+        eprintln!("  {}", algorithm.name());
+    }
+    process::exit(2);
+}
+
+fn parse_args() -> (kem::Algorithm, u32, Option<u32>) {
+    let mut args = std::env::args().skip(1).peekable();
+    let mut node = None;
+    if args.peek().map(String::as_str) == Some("--node") {
+        args.next();
+        node = match args.next().map(|n| n.parse()) {
+            Some(Ok(n)) if cfg!(feature = "numa") => Some(n),
+            _ => usage(),
+        };
+    }
+    let name = args.next().unwrap_or_else(|| usage());
+    let algorithm = kem::all()
+        .iter()
+        .copied()
+        .find(|alg| alg.name().eq_ignore_ascii_case(&name))
+        .unwrap_or_else(|| {
+            eprintln!("unknown algorithm {:?}", name);
+            usage()
+        });
+    let iterations = match args.next() {
+        None => 10,
+        Some(n) => match n.parse() {
+            Ok(n) if n > 0 => n,
+            _ => {
+                eprintln!("iterations must be a positive integer, got {:?}", n);
+                usage()
+            }
+        },
+    };
+    if args.next().is_some() {
+        usage();
+    }
+    (algorithm, iterations, node)
+}
+
+#[cfg(feature = "numa")]
+fn new_kem(algorithm: kem::Algorithm, node: Option<u32>) -> oqs::Result<Kem> {
+    match node {
+        Some(node) => Kem::new_on_node(algorithm, node),
+        None => Kem::new(algorithm),
+    }
+}
+
+#[cfg(not(feature = "numa"))]
+fn new_kem(algorithm: kem::Algorithm, _node: Option<u32>) -> oqs::Result<Kem> {
+    Kem::new(algorithm)
+}
+
+fn report(operation: &str, iterations: u32, elapsed: Duration) {
+    println!(
+        "{:<12} {:>10.1} ops/s ({:?} per op)",
+        operation,
+        f64::from(iterations) / elapsed.as_secs_f64(),
+        elapsed / iterations
+    );
+}
+
+fn main() -> oqs::Result<()> {
+    let (algorithm, iterations, node) = parse_args();
+    oqs::init();
+    let kem = new_kem(algorithm, node)?;
+
+    let start = Instant::now();
+    kem.init()?;
+    println!("{} init: {:?}", algorithm.name(), start.elapsed());
+
+    let mut keypairs = Vec::with_capacity(iterations as usize);
+    let start = Instant::now();
+    for _ in 0..iterations {
+        keypairs.push(kem.keypair()?);
+    }
+    report("keypair", iterations, start.elapsed());
+
+    if algorithm.requires_init() {
+        // Rotating keys without and with rebuilding the precomputation
+        let start = Instant::now();
+        for _ in 0..iterations {
+            kem.rekey()?;
+        }
+        report("rekey", iterations, start.elapsed());
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            kem.deinit()?;
+            kem.init()?;
+            kem.keypair()?;
+        }
+        report("reinit", iterations, start.elapsed());
+    }
+
+    let mut encapsulations = Vec::with_capacity(iterations as usize);
+    let start = Instant::now();
+    for (pk, _) in &keypairs {
+        encapsulations.push(kem.encapsulate(pk)?);
+    }
+    report("encapsulate", iterations, start.elapsed());
+
+    let mut decapsulations = Vec::with_capacity(iterations as usize);
+    let start = Instant::now();
+    for ((_, sk), (ct, _)) in keypairs.iter().zip(&encapsulations) {
+        decapsulations.push(kem.decapsulate(sk, ct)?);
+    }
+    report("decapsulate", iterations, start.elapsed());
+
+    // Allocator traffic: free every shared secret, or recycle it through an arena
+    let ((_, sk), (ct, _)) = (&keypairs[0], &encapsulations[0]);
+    let start = Instant::now();
+    for _ in 0..iterations {
+        drop(kem.decapsulate(sk, ct)?);
+    }
+    report("decap/alloc", iterations, start.elapsed());
+
+    // The safe API without its checks and allocation
+    let mut ss = vec![0u8; kem.length_shared_secret()];
+    let start = Instant::now();
+    for _ in 0..iterations {
+        unsafe {
+            kem.decapsulate_raw(ss.as_mut_ptr(), ct.as_ref().as_ptr(), sk.as_ref().as_ptr())?
+        };
+    }
+    report("decap/raw", iterations, start.elapsed());
+
+    #[cfg(feature = "std")]
+    {
+        let start = Instant::now();
+        kem::scratch::with_arena(|arena| -> oqs::Result<()> {
+            for _ in 0..iterations {
+                arena.recycle(kem.decapsulate(sk, ct)?.into_vec());
+            }
+            Ok(())
+        })?;
+        report("decap/arena", iterations, start.elapsed());
+    }
+
+    for ((_, encaps_ss), decaps_ss) in encapsulations.iter().zip(&decapsulations) {
+        assert_eq!(encaps_ss, decaps_ss, "shared secrets differ");
+    }
+    Ok(())
+}
@@ -0,0 +1,98 @@
+//! Feed arbitrary bytes to the KEM parsing and decapsulation paths
+//!
+//! The first byte selects the algorithm whose length checks parse the input,
+//! the second the algorithm that operates on the parsed objects, so buffers
+//! parsed for one algorithm are also handed to another.
+//! Valid lengths must produce a result, invalid ones an error; nothing may panic.
+#![no_main]
+use std::sync::OnceLock;
+
+use libfuzzer_sys::fuzz_target;
+use oqs::kem::{self, Kem, PublicKey, SecretKey};
+use oqs::Error;
+
+/// An algorithm and a key pair for it, generated once per fuzzing process
+struct Fixture {
+    kem: Kem,
+    pk: PublicKey,
+    sk: SecretKey,
+}
+
+fn fixtures() -> &'static [Fixture] {
+    static FIXTURES: OnceLock<Vec<Fixture>> = OnceLock::new();
+    FIXTURES.get_or_init(|| {
+        oqs::init();
+        kem::all()
+            .iter()
+            .filter(|alg| alg.is_enabled() && !alg.is_nike())
+            .map(|&alg| {
+                let kem = Kem::new(alg).unwrap();
+                kem.init().unwrap();
+                let (pk, sk) = kem.keypair().unwrap();
+                Fixture { kem, pk, sk }
+            })
+            .collect()
+    })
+}
+
+fn is_length_error(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::InvalidLength { .. }
+            | Error::InvalidCiphertextLength { .. }
+            | Error::InvalidSecretKeyLength { .. }
+    )
+}
+
+fuzz_target!(|data: &[u8]| {
+    let fixtures = fixtures();
+    if fixtures.is_empty() || data.len() < 2 {
+        return;
+    }
+    let parser = &fixtures[data[0] as usize % fixtures.len()];
+    let target = &fixtures[data[1] as usize % fixtures.len()];
+    let data = &data[2..];
+
+    if let Some(ct) = parser.kem.ciphertext_from_bytes(data) {
+        match target.kem.decapsulate(&target.sk, ct) {
+            Ok(ss) => assert_eq!(ss.len(), target.kem.length_shared_secret()),
+            Err(error) => {
+                assert!(is_length_error(&error), "{:?}", error);
+                assert_ne!(ct.len(), target.kem.length_ciphertext());
+            }
+        }
+        let mut ss = vec![0; target.kem.length_shared_secret()];
+        let result = target.kem.decapsulate_into(&target.sk, ct, &mut ss);
+        assert!(result.is_ok() || is_length_error(&result.unwrap_err()));
+        let result = target.kem.decapsulate_and_confirm(&target.sk, ct, &ss);
+        assert!(result.is_ok() || is_length_error(&result.unwrap_err()));
+    } else {
+        assert!(parser.kem.try_ciphertext_ref(data).is_err());
+    }
+
+    if let Some(pk) = parser.kem.public_key_from_bytes(data) {
+        match target.kem.encapsulate(pk) {
+            Ok((ct, ss)) => {
+                assert_eq!(ct.len(), target.kem.length_ciphertext());
+                assert_eq!(ss.len(), target.kem.length_shared_secret());
+            }
+            Err(error) => {
+                assert!(is_length_error(&error), "{:?}", error);
+                assert_ne!(pk.len(), target.kem.length_public_key());
+            }
+        }
+    } else {
+        assert!(parser.kem.try_public_key_ref(data).is_err());
+    }
+
+    if let Some(sk) = parser.kem.secret_key_from_bytes(data) {
+        let result = target
+            .kem
+            .decapsulate(sk, &target.kem.encapsulate(&target.pk).unwrap().0);
+        assert!(result.is_ok() || is_length_error(&result.unwrap_err()));
+    }
+
+    let _ = parser.kem.public_key_from_bytes_with_crc(data);
+    let _ = parser.kem.public_key_take(data);
+    let _ = oqs::format::read_keypair(data);
+});
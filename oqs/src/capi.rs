@@ -0,0 +1,207 @@
+//! C interface to the KEM API
+//!
+//! Exposes [`Kem`] to C through `#[no_mangle]` functions, so C code gets the
+//! length checks of this crate instead of calling liboqs directly.
+//!
+//! # Contract
+//!
+//! * Algorithms are identified by their null-terminated liboqs name, see [`Algorithm::name`](crate::kem::Algorithm::name).
+//! * Every function returns [`OQS_RS_OK`] on success, or one of the negative
+//!   `OQS_RS_*` error codes.
+//! * Input buffers are passed as a pointer and a length. They are only read
+//!   during the call, and must be valid for reads of that many bytes.
+//! * Output buffers are passed as a pointer and a pointer to a length.
+//!   On entry the length is the size of the buffer in bytes, on success it is
+//!   set to the number of bytes written. If a buffer is too small, nothing is
+//!   written, the length is set to the required size and
+//!   [`OQS_RS_BUFFER_TOO_SMALL`] is returned. Use [`oqs_rs_kem_lengths`] to size
+//!   the buffers up front.
+//! * The caller owns all buffers; nothing is allocated for the caller to free.
+//! * The functions are thread-safe, and call [`crate::init`] themselves.
+//!
+//! This module requires the `capi` feature.
+use core::slice;
+
+use libc::{c_char, c_int};
+
+use crate::kem::Kem;
+use crate::*;
+
+/// The operation succeeded
+pub const OQS_RS_OK: c_int = 0;
+/// liboqs reported an error, or the operation failed otherwise
+pub const OQS_RS_ERROR: c_int = -1;
+/// The algorithm is unknown or disabled
+pub const OQS_RS_ALGORITHM_DISABLED: c_int = -2;
+/// An input does not have the length the algorithm requires
+pub const OQS_RS_INVALID_LENGTH: c_int = -3;
+/// An output buffer is too small; its length was set to the required size
+pub const OQS_RS_BUFFER_TOO_SMALL: c_int = -4;
+/// A pointer argument was null
+pub const OQS_RS_NULL_POINTER: c_int = -5;
+
+fn error_code(error: Error) -> c_int {
+    match error {
+        Error::AlgorithmDisabled
+        | Error::MissingDependency(_)
+        | Error::AlgorithmUnavailable { .. } => OQS_RS_ALGORITHM_DISABLED,
+        Error::InvalidLength { .. }
+        | Error::InvalidCiphertextLength { .. }
+        | Error::InvalidSecretKeyLength { .. } => OQS_RS_INVALID_LENGTH,
+        _ => OQS_RS_ERROR,
+    }
+}
+
+/// Run `f`, turning errors and panics into error codes
+fn guard(f: impl FnOnce() -> core::result::Result<(), c_int>) -> c_int {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(Ok(())) => OQS_RS_OK,
+        Ok(Err(code)) => code,
+        Err(_) => OQS_RS_ERROR,
+    }
+}
+
+/// Construct and, if needed, initialize the `Kem` for the algorithm named `alg`
+unsafe fn kem_from_name(alg: *const c_char) -> core::result::Result<Kem, c_int> {
+    if alg.is_null() {
+        return Err(OQS_RS_NULL_POINTER);
+    }
+    let name = std::ffi::CStr::from_ptr(alg).to_bytes();
+    let algorithm = kem::all()
+        .iter()
+        .copied()
+        .find(|alg| alg.name().as_bytes() == name)
+        .ok_or(OQS_RS_ALGORITHM_DISABLED)?;
+    crate::init();
+    let kem = Kem::new(algorithm).map_err(error_code)?;
+    if algorithm.requires_init() {
+        kem.init().map_err(error_code)?;
+    }
+    Ok(kem)
+}
+
+unsafe fn input<'a>(ptr: *const u8, len: usize) -> core::result::Result<&'a [u8], c_int> {
+    if ptr.is_null() {
+        return Err(OQS_RS_NULL_POINTER);
+    }
+    Ok(slice::from_raw_parts(ptr, len))
+}
+
+/// Check an output buffer and report the length that will be written
+unsafe fn output<'a>(
+    ptr: *mut u8,
+    len: *mut usize,
+    required: usize,
+) -> core::result::Result<&'a mut [u8], c_int> {
+    if ptr.is_null() || len.is_null() {
+        return Err(OQS_RS_NULL_POINTER);
+    }
+    let capacity = *len;
+    *len = required;
+    if capacity < required {
+        return Err(OQS_RS_BUFFER_TOO_SMALL);
+    }
+    Ok(slice::from_raw_parts_mut(ptr, required))
+}
+
+/// Get the lengths in bytes of the public key, secret key, ciphertext and shared secret
+///
+/// # Safety
+/// `alg` must be a null-terminated string; the other arguments must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn oqs_rs_kem_lengths(
+    alg: *const c_char,
+    pk_len: *mut usize,
+    sk_len: *mut usize,
+    ct_len: *mut usize,
+    ss_len: *mut usize,
+) -> c_int {
+    guard(|| {
+        let kem = kem_from_name(alg)?;
+        if pk_len.is_null() || sk_len.is_null() || ct_len.is_null() || ss_len.is_null() {
+            return Err(OQS_RS_NULL_POINTER);
+        }
+        *pk_len = kem.length_public_key();
+        *sk_len = kem.length_secret_key();
+        *ct_len = kem.length_ciphertext();
+        *ss_len = kem.length_shared_secret();
+        Ok(())
+    })
+}
+
+/// Generate a key pair
+///
+/// # Safety
+/// `alg` must be a null-terminated string; see the [module documentation](self) for the buffers.
+#[no_mangle]
+pub unsafe extern "C" fn oqs_rs_kem_keypair(
+    alg: *const c_char,
+    pk_out: *mut u8,
+    pk_len: *mut usize,
+    sk_out: *mut u8,
+    sk_len: *mut usize,
+) -> c_int {
+    guard(|| {
+        let kem = kem_from_name(alg)?;
+        let pk_out = output(pk_out, pk_len, kem.length_public_key())?;
+        let sk_out = output(sk_out, sk_len, kem.length_secret_key())?;
+        let (pk, sk) = kem.keypair().map_err(error_code)?;
+        pk_out.copy_from_slice(pk.as_ref());
+        let mut sk = sk.into_vec();
+        sk_out.copy_from_slice(&sk);
+        crate::util::scrub(&mut sk);
+        Ok(())
+    })
+}
+
+/// Encapsulate to a public key
+///
+/// # Safety
+/// `alg` must be a null-terminated string; see the [module documentation](self) for the buffers.
+#[no_mangle]
+pub unsafe extern "C" fn oqs_rs_kem_encapsulate(
+    alg: *const c_char,
+    pk: *const u8,
+    pk_len: usize,
+    ct_out: *mut u8,
+    ct_len: *mut usize,
+    ss_out: *mut u8,
+    ss_len: *mut usize,
+) -> c_int {
+    guard(|| {
+        let kem = kem_from_name(alg)?;
+        let pk = kem
+            .try_public_key_ref(input(pk, pk_len)?)
+            .map_err(error_code)?;
+        let ct_out = output(ct_out, ct_len, kem.length_ciphertext())?;
+        let ss_out = output(ss_out, ss_len, kem.length_shared_secret())?;
+        kem.encapsulate_into(pk, ct_out, ss_out).map_err(error_code)
+    })
+}
+
+/// Decapsulate a ciphertext
+///
+/// # Safety
+/// `alg` must be a null-terminated string; see the [module documentation](self) for the buffers.
+#[no_mangle]
+pub unsafe extern "C" fn oqs_rs_kem_decapsulate(
+    alg: *const c_char,
+    sk: *const u8,
+    sk_len: usize,
+    ct: *const u8,
+    ct_len: usize,
+    ss_out: *mut u8,
+    ss_len: *mut usize,
+) -> c_int {
+    guard(|| {
+        let kem = kem_from_name(alg)?;
+        let sk = kem
+            .try_secret_key_ref(input(sk, sk_len)?)
+            .map_err(error_code)?;
+        let ct = kem
+            .try_ciphertext_ref(input(ct, ct_len)?)
+            .map_err(error_code)?;
+        let ss_out = output(ss_out, ss_len, kem.length_shared_secret())?;
+        kem.decapsulate_into(sk, ct, ss_out).map_err(error_code)
+    })
+}
@@ -0,0 +1,231 @@
+//! Running blocking operations off an async reactor
+//!
+//! [`Kem::encapsulate_async`](crate::kem::Kem::encapsulate_async) and
+//! [`Kem::decapsulate_async`](crate::kem::Kem::decapsulate_async) hand the
+//! operation to an [`Executor`] and return a future that completes once it ran.
+//! This keeps slow operations, such as Classic McEliece, from blocking the
+//! thread that polls the future, without tying this crate to an async runtime.
+//! They are unrelated to the precomputation-based [`Kem::async_encapsulate`](crate::kem::Kem::async_encapsulate).
+//!
+//! By default the operations run on a small internal thread pool.
+//! [`set_executor`] replaces it, e.g. by the blocking pool of your runtime:
+//!
+//! ```
+//! use oqs::executor::{set_executor, Executor, Job};
+//!
+//! /// Runs every job on a fresh thread
+//! struct ThreadPerJob;
+//!
+//! impl Executor for ThreadPerJob {
+//!     fn execute(&self, job: Job) {
+//!         std::thread::spawn(job);
+//!     }
+//! }
+//!
+//! set_executor(ThreadPerJob);
+//! ```
+//!
+//! With tokio, `execute` would call `tokio::task::spawn_blocking(job)`.
+//!
+//! This module requires the `async` feature.
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::sync::{mpsc, Mutex, MutexGuard};
+
+use crate::*;
+
+/// A blocking operation to be run by an [`Executor`]
+pub type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Runs the blocking operations behind the futures of this crate
+pub trait Executor: Send + Sync {
+    /// Run `job` to completion, on a thread where blocking is acceptable
+    ///
+    /// If `job` is dropped without being run, its future resolves to [`Error::Error`].
+    fn execute(&self, job: Job);
+}
+
+/// Number of threads of the default executor
+const POOL_SIZE: usize = 4;
+
+/// The default executor: a fixed number of threads taking jobs from a queue
+struct ThreadPool(Mutex<mpsc::Sender<Job>>);
+
+impl ThreadPool {
+    fn new(size: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..size {
+            let receiver = Arc::clone(&receiver);
+            std::thread::Builder::new()
+                .name(alloc::format!("oqs-executor-{}", i))
+                .spawn(move || loop {
+                    let job = match receiver.lock().unwrap_or_else(|e| e.into_inner()).recv() {
+                        Ok(job) => job,
+                        Err(_) => return,
+                    };
+                    // A panicking job resolves its future to an error; keep the thread alive
+                    let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(job));
+                })
+                .expect("failed to spawn executor thread");
+        }
+        ThreadPool(Mutex::new(sender))
+    }
+}
+
+impl Executor for ThreadPool {
+    fn execute(&self, job: Job) {
+        // The workers never exit while the sender exists, so sending only fails
+        // if all of them died; the job is then dropped and its future errors
+        let _ = self.0.lock().unwrap_or_else(|e| e.into_inner()).send(job);
+    }
+}
+
+/// The installed executor, created on first use if none was set
+static EXECUTOR: Mutex<Option<Arc<dyn Executor>>> = Mutex::new(None);
+
+fn lock() -> MutexGuard<'static, Option<Arc<dyn Executor>>> {
+    EXECUTOR.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Run all operations started from now on through `executor`
+///
+/// Operations that were already started finish on the previous executor.
+pub fn set_executor(executor: impl Executor + 'static) {
+    *lock() = Some(Arc::new(executor));
+}
+
+fn current() -> Arc<dyn Executor> {
+    Arc::clone(lock().get_or_insert_with(|| Arc::new(ThreadPool::new(POOL_SIZE))))
+}
+
+/// State shared by a [`Task`] and the job computing its result
+struct Shared<T> {
+    result: Option<Result<T>>,
+    waker: Option<Waker>,
+}
+
+/// Stores the result of a job, or an error if the job is dropped before finishing
+struct Completer<T>(Arc<Mutex<Shared<T>>>);
+
+impl<T> Completer<T> {
+    fn complete(&self, result: Result<T>) {
+        let mut shared = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if shared.result.is_none() {
+            shared.result = Some(result);
+        }
+        let waker = shared.waker.take();
+        drop(shared);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Drop for Completer<T> {
+    fn drop(&mut self) {
+        self.complete(Err(Error::Error));
+    }
+}
+
+/// Future resolving to the result of a job handed to the [`Executor`]
+pub(crate) struct Task<T>(Arc<Mutex<Shared<T>>>);
+
+impl<T> Future for Task<T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        match shared.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Run `f` on the current executor
+pub(crate) fn spawn<T, F>(f: F) -> Task<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    let shared = Arc::new(Mutex::new(Shared {
+        result: None,
+        waker: None,
+    }));
+    let completer = Completer(Arc::clone(&shared));
+    current().execute(Box::new(move || completer.complete(f())));
+    Task(shared)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use std::task::Wake;
+    use std::thread::{self, Thread};
+
+    /// Serializes the tests that replace the executor
+    pub(crate) static EXECUTOR_TEST_LOCK: Mutex<()> = Mutex::new(());
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Minimal executor for futures, parking the thread until woken
+    pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    /// Drops every job without running it
+    struct Discard;
+
+    impl Executor for Discard {
+        fn execute(&self, _job: Job) {}
+    }
+
+    #[test]
+    fn test_spawn() {
+        let _guard = EXECUTOR_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        assert_eq!(block_on(spawn(|| Ok(42))).unwrap(), 42);
+        assert!(matches!(
+            block_on(spawn(|| Err::<(), _>(Error::InvalidFormat))),
+            Err(Error::InvalidFormat)
+        ));
+    }
+
+    #[test]
+    fn test_panicking_job() {
+        let _guard = EXECUTOR_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let task = spawn(|| -> Result<()> { panic!("job panicked") });
+        assert!(matches!(block_on(task), Err(Error::Error)));
+        // The pool survives the panic
+        assert_eq!(block_on(spawn(|| Ok(1))).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_dropped_job() {
+        let _guard = EXECUTOR_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        set_executor(Discard);
+        let result = block_on(spawn(|| Ok(1)));
+        *lock() = None;
+        assert!(matches!(result, Err(Error::Error)));
+    }
+}
@@ -0,0 +1,164 @@
+//! Compile-time availability of algorithm families
+//!
+//! Each constant is `true` if the corresponding feature of this crate is enabled,
+//! so code can check for a family without repeating the feature name:
+//!
+//! ```
+//! if oqs::features::HAS_KYBER {
+//!     assert!(oqs::kem::Algorithm::Kyber512.is_enabled());
+//! }
+//! ```
+//!
+//! An enabled feature only means the family is compiled in: individual algorithms
+//! may still be disabled in liboqs, see [`kem::Algorithm::is_enabled`](crate::kem::Algorithm::is_enabled).
+//!
+//! [`build_info`] collects all enabled features, e.g. to include them in bug reports.
+use alloc::vec::Vec;
+
+#[cfg(not(feature = "std"))]
+use cstr_core::CStr;
+#[cfg(feature = "std")]
+use std::ffi::CStr;
+
+macro_rules! has_features {
+    ($($name: ident: $feat: literal,)*) => {
+        $(
+            #[doc = concat!("Whether the `", $feat, "` feature is enabled")]
+            pub const $name: bool = cfg!(feature = $feat);
+        )*
+
+        /// The algorithm family features, and whether they are enabled
+        const FAMILIES: &[(&str, bool)] = &[$(($feat, $name),)*];
+    };
+}
+
+/// The optional features other than the algorithm families, and whether they are enabled
+const OPTIONAL: &[(&str, bool)] = &[
+    ("std", cfg!(feature = "std")),
+    ("openssl", cfg!(feature = "openssl")),
+    ("serde", cfg!(feature = "serde")),
+    ("zeroize", cfg!(feature = "zeroize")),
+    ("tracing", cfg!(feature = "tracing")),
+    ("metrics", cfg!(feature = "metrics")),
+    ("async", cfg!(feature = "async")),
+    ("hex", cfg!(feature = "hex")),
+    ("shares", cfg!(feature = "shares")),
+    ("capi", cfg!(feature = "capi")),
+    ("hybrid", cfg!(feature = "hybrid")),
+    ("encrypted-keys", cfg!(feature = "encrypted-keys")),
+    ("mmap", cfg!(feature = "mmap")),
+    ("secure-alloc", cfg!(feature = "secure-alloc")),
+    ("numa", cfg!(feature = "numa")),
+    ("hkdf", cfg!(feature = "hkdf")),
+    ("kdf", cfg!(feature = "kdf")),
+    ("kemtls", cfg!(feature = "kemtls")),
+    ("test-utils", cfg!(feature = "test-utils")),
+    ("arbitrary", cfg!(feature = "arbitrary")),
+    ("non_portable", cfg!(feature = "non_portable")),
+];
+
+/// How this crate was built, see [`build_info`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BuildInfo {
+    /// The enabled algorithm family features, such as `"kyber"` or `"sike"`
+    pub families: Vec<&'static str>,
+    /// All other enabled optional features, such as `"std"` or `"hybrid"`
+    pub features: Vec<&'static str>,
+    /// Whether the `std` feature is enabled
+    pub std: bool,
+    /// Whether the `serde` feature is enabled
+    pub serde: bool,
+    /// Whether the `zeroize` feature is enabled
+    pub zeroize: bool,
+    /// The version of the linked liboqs
+    pub liboqs_version: &'static str,
+}
+
+/// The features this crate was compiled with and the liboqs version it links
+///
+/// Print this when reporting a bug:
+///
+/// ```
+/// println!("{:?}", oqs::build_info());
+/// ```
+pub fn build_info() -> BuildInfo {
+    let enabled = |features: &[(&'static str, bool)]| {
+        features
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| *name)
+            .collect()
+    };
+    // SAFETY: liboqs returns a static, null-terminated string
+    let version = unsafe { CStr::from_ptr(crate::ffi::common::OQS_version()) };
+    BuildInfo {
+        families: enabled(FAMILIES),
+        features: enabled(OPTIONAL),
+        std: cfg!(feature = "std"),
+        serde: cfg!(feature = "serde"),
+        zeroize: cfg!(feature = "zeroize"),
+        liboqs_version: version.to_str().expect("the liboqs version must be UTF-8"),
+    }
+}
+
+has_features! {
+    HAS_BIKE: "bike",
+    HAS_CLASSIC_MCELIECE: "classic_mceliece",
+    HAS_CSIDH: "csidh",
+    HAS_FRODOKEM: "frodokem",
+    HAS_HQC: "hqc",
+    HAS_KYBER: "kyber",
+    HAS_NTRU: "ntru",
+    HAS_NTRUPRIME: "ntruprime",
+    HAS_SABER: "saber",
+    HAS_SIDH: "sidh",
+    HAS_SIKE: "sike",
+    HAS_DILITHIUM: "dilithium",
+    HAS_FALCON: "falcon",
+    HAS_PICNIC: "picnic",
+    HAS_RAINBOW: "rainbow",
+    HAS_SPHINCS: "sphincs",
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kem::{self, Family};
+
+    #[test]
+    fn test_kem_families() {
+        crate::init();
+        for alg in kem::all() {
+            let compiled_in = match alg.family() {
+                Family::Bike => HAS_BIKE,
+                Family::ClassicMcEliece => HAS_CLASSIC_MCELIECE,
+                Family::Csidh => HAS_CSIDH,
+                Family::FrodoKem => HAS_FRODOKEM,
+                Family::Hqc => HAS_HQC,
+                Family::Kyber => HAS_KYBER,
+                Family::Ntru => HAS_NTRU,
+                Family::NtruPrime => HAS_NTRUPRIME,
+                Family::Saber => HAS_SABER,
+                Family::Sidh => HAS_SIDH,
+                Family::Sike => HAS_SIKE,
+            };
+            if alg.is_enabled() {
+                assert!(compiled_in, "{:?} is enabled without its feature", alg);
+            }
+        }
+    }
+
+    #[test]
+    fn test_build_info() {
+        let info = build_info();
+        assert_eq!(info.families.contains(&"kyber"), HAS_KYBER);
+        assert_eq!(info.families.contains(&"sike"), HAS_SIKE);
+        assert_eq!(info.features.contains(&"std"), info.std);
+        assert_eq!(info.std, cfg!(feature = "std"));
+        assert!(!info
+            .families
+            .iter()
+            .any(|name| info.features.contains(name)));
+        assert!(!info.liboqs_version.is_empty());
+    }
+}
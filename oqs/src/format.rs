@@ -0,0 +1,184 @@
+//! Versioned container for storing KEM key pairs
+//!
+//! The container is self-describing, so keys written by one version of this crate
+//! can be read by later versions. Version 1 of the format is, in order:
+//!
+//! | Field          | Size             | Contents                                   |
+//! |----------------|------------------|--------------------------------------------|
+//! | magic          | 4                | `b"OQSK"`                                  |
+//! | version        | 1                | `1`                                        |
+//! | name length    | 1                | length `n` of the algorithm name           |
+//! | name           | `n`              | liboqs name of the algorithm, e.g. `Kyber512` |
+//! | pk length      | 4                | length `p` of the public key, big endian   |
+//! | public key     | `p`              |                                            |
+//! | sk length      | 4                | length `s` of the secret key, big endian   |
+//! | secret key     | `s`              |                                            |
+//! | checksum       | 4                | CRC-32 of all preceding bytes, big endian  |
+//!
+//! The algorithm is stored by its liboqs name rather than by its position in
+//! [`Algorithm`], as the latter changes when algorithms are added.
+//! The checksum only detects accidental corruption; the container is not encrypted
+//! or authenticated, so the secret key must be protected by other means.
+use alloc::vec::Vec;
+
+use crate::kem::{self, Algorithm, Kem, PublicKey, SecretKey};
+use crate::*;
+
+/// Identifies the container
+pub const MAGIC: [u8; 4] = *b"OQSK";
+
+/// The version of the container written by [`write_keypair`]
+pub const VERSION: u8 = 1;
+
+/// Serialize a key pair of `algorithm` into a container
+pub fn write_keypair(algorithm: Algorithm, pk: &PublicKey, sk: &SecretKey) -> Vec<u8> {
+    let name = algorithm.name().as_bytes();
+    let mut buf = Vec::with_capacity(4 + 1 + 1 + name.len() + 4 + pk.len() + 4 + sk.len() + 4);
+    buf.extend_from_slice(&MAGIC);
+    buf.push(VERSION);
+    buf.push(name.len() as u8);
+    buf.extend_from_slice(name);
+    for key in [pk.as_ref(), sk.as_ref()].iter() {
+        buf.extend_from_slice(&(key.len() as u32).to_be_bytes());
+        buf.extend_from_slice(key);
+    }
+    let crc = crate::util::crc32(&buf);
+    buf.extend_from_slice(&crc.to_be_bytes());
+    buf
+}
+
+/// Parse a container written by [`write_keypair`]
+///
+/// Returns [`Error::InvalidFormat`] if the magic, version or structure is wrong
+/// or the algorithm is unknown, [`Error::ChecksumMismatch`] if the container is
+/// corrupted, and [`Error::InvalidLength`] if a key does not have the length the
+/// algorithm requires. The algorithm must be enabled to check the key lengths.
+pub fn read_keypair(buf: &[u8]) -> Result<(Algorithm, PublicKey, SecretKey)> {
+    if buf.len() < MAGIC.len() + 1 + 4 || buf[..MAGIC.len()] != MAGIC {
+        return Err(Error::InvalidFormat);
+    }
+    if buf[MAGIC.len()] != VERSION {
+        return Err(Error::InvalidFormat);
+    }
+    let (body, crc) = buf.split_at(buf.len() - 4);
+    if crate::util::crc32(body) != u32::from_be_bytes([crc[0], crc[1], crc[2], crc[3]]) {
+        return Err(Error::ChecksumMismatch);
+    }
+
+    let mut reader = Reader(&body[MAGIC.len() + 1..]);
+    let name_len = reader.take(1)?[0] as usize;
+    let name = reader.take(name_len)?;
+    let algorithm = kem::all()
+        .iter()
+        .copied()
+        .find(|alg| alg.name().as_bytes() == name)
+        .ok_or(Error::InvalidFormat)?;
+    let pk = reader.take_prefixed()?;
+    let sk = reader.take_prefixed()?;
+    if !reader.0.is_empty() {
+        return Err(Error::InvalidFormat);
+    }
+
+    let kem = Kem::new(algorithm)?;
+    let pk = kem.try_public_key_ref(pk)?.to_owned();
+    let sk = kem.try_secret_key_ref(sk)?.to_owned();
+    Ok((algorithm, pk, sk))
+}
+
+/// Reads fields from the front of a container
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(Error::InvalidFormat);
+        }
+        let (field, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(field)
+    }
+
+    /// Read a field preceded by its big-endian `u32` length
+    fn take_prefixed(&mut self) -> Result<&'a [u8]> {
+        let len = self.take(4)?;
+        let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]);
+        self.take(len as usize)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Replace the checksum after modifying a container
+    fn fix_crc(buf: &mut Vec<u8>) {
+        buf.truncate(buf.len() - 4);
+        let crc = crate::util::crc32(buf);
+        buf.extend_from_slice(&crc.to_be_bytes());
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_roundtrip() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (pk, sk) = kem.keypair()?;
+        let buf = write_keypair(Algorithm::Kyber512, &pk, &sk);
+        assert_eq!(buf[..4], MAGIC);
+        let (algorithm, pk2, sk2) = read_keypair(&buf)?;
+        assert_eq!(algorithm, Algorithm::Kyber512);
+        assert_eq!(pk2, pk);
+        assert_eq!(sk2, sk);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_reject_corrupted() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (pk, sk) = kem.keypair()?;
+        let buf = write_keypair(Algorithm::Kyber512, &pk, &sk);
+
+        let mut bad_magic = buf.clone();
+        bad_magic[0] ^= 1;
+        fix_crc(&mut bad_magic);
+        assert!(matches!(
+            read_keypair(&bad_magic),
+            Err(Error::InvalidFormat)
+        ));
+
+        let mut bad_version = buf.clone();
+        bad_version[4] = VERSION + 1;
+        fix_crc(&mut bad_version);
+        assert!(matches!(
+            read_keypair(&bad_version),
+            Err(Error::InvalidFormat)
+        ));
+
+        let mut unknown_algorithm = buf.clone();
+        unknown_algorithm[6] ^= 0x20;
+        fix_crc(&mut unknown_algorithm);
+        assert!(matches!(
+            read_keypair(&unknown_algorithm),
+            Err(Error::InvalidFormat)
+        ));
+
+        let mut flipped = buf.clone();
+        flipped[100] ^= 1;
+        assert!(matches!(
+            read_keypair(&flipped),
+            Err(Error::ChecksumMismatch)
+        ));
+
+        let mut truncated = buf[..buf.len() - 10].to_vec();
+        fix_crc(&mut truncated);
+        assert!(matches!(
+            read_keypair(&truncated),
+            Err(Error::InvalidFormat)
+        ));
+
+        assert!(matches!(read_keypair(&buf[..3]), Err(Error::InvalidFormat)));
+        Ok(())
+    }
+}
@@ -2,9 +2,18 @@
 //!
 //! See [`Kem`] for the main functionality.
 //! [`Algorithm`] lists the available algorithms.
+//!
+//! Keys, ciphertexts and secrets are plain byte strings. They have no byte order
+//! and can be moved between big- and little-endian machines as they are.
+//!
+//! CSIDH is a non-interactive key exchange rather than a KEM, see [`Algorithm::is_nike`].
+use alloc::collections::BTreeMap;
+#[cfg(feature = "async")]
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 
 use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 #[cfg(not(feature = "std"))]
 use cstr_core::CStr;
@@ -18,21 +27,221 @@ use crate::ffi::kem as ffi;
 use crate::newtype_buffer;
 use crate::*;
 
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+mod builder;
+#[cfg(feature = "std")]
+mod compare;
+#[cfg(feature = "encrypted-keys")]
+pub mod encrypted;
+#[cfg(feature = "hybrid")]
+pub mod hybrid;
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "std")]
+pub mod scratch;
+#[cfg(feature = "secure-alloc")]
+pub mod secure;
+#[cfg(feature = "shares")]
+pub mod shares;
+#[cfg(feature = "test-utils")]
+mod test_utils;
+
+pub use builder::KemBuilder;
+#[cfg(feature = "std")]
+pub use compare::{compare, ComparisonRow};
+#[cfg(feature = "secure-alloc")]
+pub use secure::SecureBytes;
+
 newtype_buffer!(PublicKey, PublicKeyRef);
 newtype_buffer!(SecretKey, SecretKeyRef);
 newtype_buffer!(Ciphertext, CiphertextRef);
 newtype_buffer!(SharedSecret, SharedSecretRef);
 newtype_buffer!(EphemeralSecret, EphemeralSecretRef);
+newtype_buffer!(CompactSecretKey, CompactSecretKeyRef);
+
+/// Display the public buffer types as lowercase hex
+///
+/// Deliberately not implemented for the secret types.
+#[cfg(feature = "hex")]
+macro_rules! hex_display {
+    ($($name: ident, $name_ref: ident);*) => {
+        $(
+            impl core::fmt::Display for $name {
+                fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                    crate::util::write_hex(f, &self.bytes)
+                }
+            }
+
+            impl core::fmt::Display for $name_ref<'_> {
+                fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                    crate::util::write_hex(f, self.bytes)
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "hex")]
+hex_display!(PublicKey, PublicKeyRef; Ciphertext, CiphertextRef);
+
+impl SharedSecret {
+    /// Overwrite the shared secret with zeroes, keeping the buffer for reuse
+    ///
+    /// The length and allocation stay the same, so the buffer can be refilled
+    /// through [`SharedSecret::as_mut_bytes`] and [`Kem::decapsulate_into`].
+    #[cfg(feature = "zeroize")]
+    pub fn zeroize_and_reset(&mut self) {
+        use zeroize::Zeroize;
+        self.bytes.as_mut_slice().zeroize();
+    }
+
+    /// Mutable access to the shared secret, to refill it in place
+    pub fn as_mut_bytes(&mut self) -> &mut [u8] {
+        &mut self.bytes
+    }
+
+    /// Derive `out_len` bytes of key material labeled with `info`
+    ///
+    /// Implements HKDF-Expand (RFC 5869) with SHA-256, using the shared secret
+    /// as the pseudorandom key. Use different `info` labels to derive several
+    /// independent keys from one shared secret. None of the KEMs take a context
+    /// string, so this is also where to separate protocols that share a key pair:
+    /// put a label that is different for every protocol in `info`.
+    /// The output and the intermediate blocks are zeroized when dropped.
+    ///
+    /// # Panics
+    /// If `out_len` is more than 255 * 32 bytes, the maximum HKDF-Expand output length.
+    #[cfg(feature = "hkdf")]
+    pub fn expand(&self, info: &[u8], out_len: usize) -> zeroize::Zeroizing<Vec<u8>> {
+        let mut okm = zeroize::Zeroizing::new(alloc::vec![0; out_len]);
+        hkdf_expand(&self.bytes, info, &mut okm);
+        okm
+    }
+
+    /// Combine this shared secret with `other` into a 32-byte secret
+    ///
+    /// Computes SHA3-256(`self` || `other` || `label`), the combiner of the X-Wing
+    /// KEM (draft-connolly-cfrg-xwing-kem), so the result is secure if either input
+    /// is. For X-Wing itself, `self` is the ML-KEM secret, `other` the X25519 secret
+    /// and `label` the X25519 ciphertext, the X25519 public key and `b"\\.//^\\"`,
+    /// in that order. Use a `label` unique to your protocol otherwise, and include
+    /// the ciphertexts in it if the KEMs are not both IND-CCA secure.
+    /// The output is zeroized when dropped.
+    #[cfg(feature = "kdf")]
+    pub fn combine(&self, other: &SharedSecret, label: &[u8]) -> zeroize::Zeroizing<Vec<u8>> {
+        use sha3::{Digest, Sha3_256};
+
+        let mut hasher = Sha3_256::new();
+        hasher.update(&self.bytes);
+        hasher.update(&other.bytes);
+        hasher.update(label);
+        zeroize::Zeroizing::new(hasher.finalize()[..].to_vec())
+    }
+}
+
+impl EphemeralSecret {
+    /// Copy the ephemeral secret out, e.g. to store it between the two halves of
+    /// a split encapsulation
+    ///
+    /// Reload it with [`Kem::ephemeral_secret_from_bytes`] or
+    /// [`Kem::try_ephemeral_secret_ref`], which check the length.
+    /// With the `zeroize` feature, the copy is zeroized when dropped.
+    ///
+    /// Anyone who obtains the ephemeral secret together with the ciphertext can
+    /// compute the shared secret. Only persist it in storage that is as well
+    /// protected as the session keys, and delete it once the encapsulation is done.
+    #[cfg(feature = "zeroize")]
+    pub fn to_bytes(&self) -> zeroize::Zeroizing<Vec<u8>> {
+        zeroize::Zeroizing::new(self.bytes.clone())
+    }
+
+    /// Copy the ephemeral secret out, e.g. to store it between the two halves of
+    /// a split encapsulation
+    ///
+    /// Reload it with [`Kem::ephemeral_secret_from_bytes`] or
+    /// [`Kem::try_ephemeral_secret_ref`], which check the length.
+    /// Enable the `zeroize` feature to have the copy zeroized when dropped.
+    ///
+    /// Anyone who obtains the ephemeral secret together with the ciphertext can
+    /// compute the shared secret. Only persist it in storage that is as well
+    /// protected as the session keys, and delete it once the encapsulation is done.
+    #[cfg(not(feature = "zeroize"))]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.bytes.clone()
+    }
+}
+
+impl SharedSecretRef<'_> {
+    /// Compare this shared secret to `other` in constant time
+    ///
+    /// Does not depend on any external crate, so it is also
+    /// available with `default-features = false`.
+    /// A length mismatch compares unequal, but does not return early.
+    pub fn ct_eq_bytes(&self, other: &[u8]) -> bool {
+        crate::util::ct_eq(self.bytes, other)
+    }
+}
+
+impl SecretKey {
+    /// The secret key without the copy of the public key it embeds
+    ///
+    /// Roughly halves the storage of secret keys for the algorithms listed at
+    /// [`Kem::public_from_secret`], where the public key is stored anyway.
+    /// [`Kem::secret_key_from_compact`] rebuilds the secret key from the compact
+    /// form and the public key. Returns [`Error::OperationNotSupported`] for the
+    /// other algorithms, and [`Error::InvalidSecretKeyLength`] if this is not a
+    /// secret key of `kem`.
+    pub fn compact(&self, kem: &Kem) -> Result<CompactSecretKey> {
+        kem.check_secret_key_length(&SecretKeyRef::from(self))?;
+        let offset = kem.embedded_public_key_offset()?;
+        let mut bytes = alloc::vec![0; kem.length_compact_secret_key()?];
+        bytes[..offset].copy_from_slice(&self.bytes[..offset]);
+        bytes[offset..].copy_from_slice(&self.bytes[offset + kem.length_public_key()..]);
+        Ok(CompactSecretKey { bytes })
+    }
+}
+
+impl SecretKeyRef<'_> {
+    /// Compare this secret key to `other` in constant time
+    ///
+    /// Use this instead of `==` when comparing secret keys, e.g. to check that a
+    /// backup was restored correctly. A length mismatch compares unequal,
+    /// but does not return early.
+    pub fn ct_eq<'b, S: Into<SecretKeyRef<'b>>>(&self, other: S) -> bool {
+        crate::util::ct_eq(self.bytes, other.into().bytes)
+    }
+}
+
+impl PublicKey {
+    /// The public key followed by its CRC-32, in big-endian byte order
+    ///
+    /// Useful to detect corruption in transit with
+    /// [`Kem::public_key_from_bytes_with_crc`].
+    pub fn with_crc(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.bytes.len() + 4);
+        buf.extend_from_slice(&self.bytes);
+        buf.extend_from_slice(&crate::util::crc32(&self.bytes).to_be_bytes());
+        buf
+    }
+}
+
+/// Maximum combined ciphertext and shared secret size that
+/// [`Kem::encapsulate_with`] handles on the stack
+pub const ENCAPSULATE_WITH_STACK_SIZE: usize = 4096;
+
+/// Maximum shared secret size that [`Kem::decapsulate_then`] handles on the stack
+const DECAPSULATE_THEN_STACK_SIZE: usize = 256;
 
 macro_rules! implement_kems {
-    { $(($feat: literal) $kem: ident: $oqs_id: ident),* $(,)? } => (
+    { $(($feat: literal) $kem: ident: $oqs_id: ident: [$($bits: literal),*]: ($pk: literal, $sk: literal, $ct: literal, $ss: literal)),* $(,)? } => (
 
         /// Supported algorithms by OQS
         ///
         /// Note that this doesn't mean that they'll be available.
         ///
         /// Optional support for `serde` if that feature is enabled.
-        #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+        #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
         #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
         #[allow(missing_docs)]
         pub enum Algorithm {
@@ -41,13 +250,112 @@ macro_rules! implement_kems {
             )*
         }
 
-        fn algorithm_to_id(algorithm: Algorithm) -> *const libc::c_char {
-            let id: &[u8] = match algorithm {
+        /// The nul-terminated id of `algorithm`
+        fn algorithm_id_bytes(algorithm: Algorithm) -> &'static [u8] {
+            match algorithm {
                 $(
                     Algorithm::$kem => &ffi::$oqs_id[..],
                 )*
-            };
-            id as *const _ as *const i8
+            }
+        }
+
+        fn algorithm_to_id(algorithm: Algorithm) -> *const libc::c_char {
+            algorithm_id_bytes(algorithm) as *const _ as *const i8
+        }
+
+        /// All algorithms, in declaration order
+        const ALGORITHMS: &[Algorithm] = &[
+            $(
+                Algorithm::$kem,
+            )*
+        ];
+
+        impl Algorithm {
+            /// Returns the family this algorithm belongs to
+            pub fn family(&self) -> Family {
+                match self {
+                    $(
+                        Algorithm::$kem => Family::from_feature($feat),
+                    )*
+                }
+            }
+
+            /// Returns true for the variants with compressed public keys and ciphertexts
+            ///
+            /// The compressed SIDH and SIKE variants are separate algorithms with their
+            /// own key format. liboqs keeps the key compression internal to them and
+            /// exports no routine to convert keys between the two forms, so keys for a
+            /// compressed variant have to be generated with that variant.
+            pub fn is_compressed(&self) -> bool {
+                match self {
+                    $(
+                        Algorithm::$kem => stringify!($kem).contains("Compressed"),
+                    )*
+                }
+            }
+
+            /// The designers' core-SVP estimate of the classical and quantum security in bits
+            ///
+            /// These are the estimates published with the round 3 submissions to NIST,
+            /// not the result of independent cryptanalysis:
+            ///
+            /// * Kyber: "CRYSTALS-Kyber Algorithm Specifications and Supporting
+            ///   Documentation", version 3.02;
+            /// * Saber: "SABER: Mod-LWR based KEM (Round 3 Submission)";
+            /// * NTRU: "NTRU Algorithm Specifications and Supporting Documentation",
+            ///   round 3, for `ntruhps2048509`, `ntruhps2048677`,
+            ///   `ntruhps4096821` and `ntruhrss701`;
+            /// * NTRU Prime: "NTRU Prime: round 3", for the 653, 761 and
+            ///   857 parameter sets.
+            ///
+            /// Returns `None` for the other algorithms, whose designers published no such
+            /// estimate, or only their NIST category (see [`Kem::claimed_nist_level`]).
+            /// Estimates from different families are not necessarily comparable, and the
+            /// claims for broken algorithms, see [`Algorithm::is_cryptographically_broken`],
+            /// no longer hold.
+            pub fn estimated_security_bits(&self) -> Option<SecurityBits> {
+                match self {
+                    $(
+                        Algorithm::$kem => SecurityBits::from_table(&[$($bits),*]),
+                    )*
+                }
+            }
+
+            /// The sizes of keys, ciphertexts and shared secrets, without calling liboqs
+            ///
+            /// Taken from a table built into this crate, so it works without allocating
+            /// and for disabled algorithms too. For enabled algorithms, it agrees with
+            /// [`Kem::sizes`].
+            pub const fn sizes(&self) -> KemSizes {
+                match self {
+                    $(
+                        Algorithm::$kem => KemSizes {
+                            public_key: $pk,
+                            secret_key: $sk,
+                            ciphertext: $ct,
+                            shared_secret: $ss,
+                        },
+                    )*
+                }
+            }
+
+            /// The name of the enum variant, for [`Algorithm::parse_relaxed`]
+            fn variant_name(&self) -> &'static str {
+                match self {
+                    $(
+                        Algorithm::$kem => stringify!($kem),
+                    )*
+                }
+            }
+
+            /// Returns true for the variants that are only secure for a single use of a key pair
+            pub fn is_1cca(&self) -> bool {
+                match self {
+                    $(
+                        Algorithm::$kem => stringify!($kem).ends_with("1CCA"),
+                    )*
+                }
+            }
         }
 
         $(
@@ -63,6 +371,7 @@ macro_rules! implement_kems {
 
                     let alg = Algorithm::$kem;
                     let kem = Kem::new(alg)?;
+                    kem.init()?;
                     let (pk, sk) = kem.keypair()?;
                     let (ct, ss1) = kem.encapsulate(&pk)?;
                     let ss2 = kem.decapsulate(&sk, &ct)?;
@@ -70,6 +379,97 @@ macro_rules! implement_kems {
                     Ok(())
                 }
 
+                #[test]
+                #[cfg(feature = $feat)]
+                fn test_encapsulate_with() -> Result<()> {
+                    crate::init();
+
+                    let kem = Kem::new(Algorithm::$kem)?;
+                    kem.init()?;
+                    let (pk, sk) = kem.keypair()?;
+                    let (ct, ss1) = kem.encapsulate_with(&pk, |ct, ss| (ct.to_owned(), ss.to_owned()))?;
+                    let ss2 = kem.decapsulate(&sk, &ct)?;
+                    assert_eq!(ss1, ss2, "shared secret not equal!");
+                    Ok(())
+                }
+
+                #[test]
+                #[cfg(feature = $feat)]
+                fn test_decapsulate_then_hmac() -> Result<()> {
+                    use hmac::{Hmac, Mac};
+                    use sha2::Sha256;
+                    crate::init();
+
+                    let hmac = |key: &[u8]| {
+                        let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+                        mac.update(b"oqs decapsulate_then");
+                        mac.finalize().into_bytes()
+                    };
+                    let kem = Kem::new(Algorithm::$kem)?;
+                    kem.init()?;
+                    let (pk, sk) = kem.keypair()?;
+                    let (ct, ss) = kem.encapsulate(&pk)?;
+                    let tag = kem.decapsulate_then(&sk, &ct, hmac)?;
+                    assert_eq!(tag, hmac(ss.as_ref()));
+                    Ok(())
+                }
+
+                #[test]
+                #[cfg(feature = $feat)]
+                fn test_public_from_secret() -> Result<()> {
+                    crate::init();
+
+                    let kem = Kem::new(Algorithm::$kem)?;
+                    kem.init()?;
+                    let (pk, sk) = kem.keypair()?;
+                    match kem.public_from_secret(&sk) {
+                        Ok(recovered) => assert_eq!(recovered, pk),
+                        Err(Error::OperationNotSupported) => {}
+                        Err(e) => return Err(e),
+                    }
+                    let short_sk = SecretKeyRef::new(&sk.as_ref()[1..]);
+                    assert!(matches!(
+                        kem.public_from_secret(short_sk),
+                        Err(Error::InvalidSecretKeyLength { .. })
+                    ));
+                    Ok(())
+                }
+
+                #[test]
+                #[cfg(feature = $feat)]
+                fn test_static_sizes() -> Result<()> {
+                    crate::init();
+
+                    let kem = Kem::new(Algorithm::$kem)?;
+                    assert_eq!(Algorithm::$kem.sizes(), kem.sizes());
+                    Ok(())
+                }
+
+                #[test]
+                #[cfg(feature = $feat)]
+                fn test_compact_secret_key() -> Result<()> {
+                    crate::init();
+
+                    let kem = Kem::new(Algorithm::$kem)?;
+                    kem.init()?;
+                    let (pk, sk) = kem.keypair()?;
+                    let compact = match sk.compact(&kem) {
+                        Ok(compact) => compact,
+                        Err(Error::OperationNotSupported) => {
+                            assert!(kem.public_from_secret(&sk).is_err());
+                            return Ok(());
+                        }
+                        Err(e) => return Err(e),
+                    };
+                    assert_eq!(compact.len(), kem.length_secret_key() - kem.length_public_key());
+                    let compact = kem.try_compact_secret_key_ref(compact.as_ref())?;
+                    let rebuilt = kem.secret_key_from_compact(compact, &pk)?;
+                    assert_eq!(rebuilt, sk);
+                    let (ct, ss) = kem.encapsulate(&pk)?;
+                    assert_eq!(kem.decapsulate(&rebuilt, &ct)?, ss);
+                    Ok(())
+                }
+
                 #[test]
                 fn test_enabled() {
                     crate::init();
@@ -100,6 +500,100 @@ macro_rules! implement_kems {
                     }
                 }
 
+                #[test]
+                fn test_try_ref_lengths() {
+                    use alloc::vec;
+                    if let Ok(kem) = Kem::new(Algorithm::$kem) {
+                        let buf = vec![0u8; kem.length_public_key() + 1];
+                        assert!(kem.try_public_key_ref(&buf[1..]).is_ok());
+                        match kem.try_public_key_ref(&buf) {
+                            Err(Error::InvalidLength { expected, got }) => {
+                                assert_eq!(expected, kem.length_public_key());
+                                assert_eq!(got, buf.len());
+                            }
+                            _ => panic!("expected InvalidLength"),
+                        }
+                    }
+                }
+
+                #[test]
+                fn test_public_key_take() {
+                    use alloc::vec;
+                    if let Ok(kem) = Kem::new(Algorithm::$kem) {
+                        let len = kem.length_public_key();
+                        let mut buf = vec![0u8; len + 3];
+                        buf[len..].copy_from_slice(&[1, 2, 3]);
+                        let (pk, rest) = kem.public_key_take(&buf).unwrap();
+                        assert_eq!(pk.len(), len);
+                        assert_eq!(rest, &[1, 2, 3]);
+
+                        let (_, rest) = kem.public_key_take(&buf[..len]).unwrap();
+                        assert!(rest.is_empty());
+                        assert!(matches!(
+                            kem.public_key_take(&buf[..len - 1]),
+                            Err(Error::InvalidLength { expected, got }) if expected == len && got == len - 1
+                        ));
+                    }
+                }
+
+                #[test]
+                #[cfg(feature = $feat)]
+                fn test_split_encapsulation_support() -> Result<()> {
+                    crate::init();
+
+                    let kem = Kem::new(Algorithm::$kem)?;
+                    if kem.length_ephemeral_secret().is_none() {
+                        assert!(matches!(
+                            kem.encapsulate_ciphertext(),
+                            Err(Error::OperationNotSupported)
+                        ));
+                        assert!(matches!(
+                            kem.try_ephemeral_secret_ref(&[]),
+                            Err(Error::OperationNotSupported)
+                        ));
+                        assert!(kem.ephemeral_secret_from_bytes(&[]).is_none());
+                    }
+                    Ok(())
+                }
+
+                #[test]
+                fn test_decapsulate_length_errors() {
+                    use alloc::vec;
+                    if let Ok(kem) = Kem::new(Algorithm::$kem) {
+                        kem.init().unwrap();
+                        let sk = vec![0u8; kem.length_secret_key()];
+                        let ct = vec![0u8; kem.length_ciphertext()];
+                        let sk = kem.secret_key_from_bytes(&sk).unwrap();
+                        let short_sk = SecretKeyRef::new(&sk[1..]);
+                        let short_ct = CiphertextRef::new(&ct[1..]);
+                        assert!(matches!(
+                            kem.decapsulate(short_sk, kem.ciphertext_from_bytes(&ct).unwrap()),
+                            Err(Error::InvalidSecretKeyLength { .. })
+                        ));
+                        assert!(matches!(
+                            kem.decapsulate(sk, short_ct),
+                            Err(Error::InvalidCiphertextLength { .. })
+                        ));
+                    }
+                }
+
+                #[test]
+                #[cfg(feature = $feat)]
+                fn test_decapsulate_and_confirm() -> Result<()> {
+                    crate::init();
+
+                    let kem = Kem::new(Algorithm::$kem)?;
+                    kem.init()?;
+                    let (pk, sk) = kem.keypair()?;
+                    let (ct, ss) = kem.encapsulate(&pk)?;
+                    assert!(kem.decapsulate_and_confirm(&sk, &ct, ss.as_ref())?);
+                    let mut wrong = ss.into_vec();
+                    wrong[0] ^= 1;
+                    assert!(!kem.decapsulate_and_confirm(&sk, &ct, &wrong)?);
+                    assert!(!kem.decapsulate_and_confirm(&sk, &ct, &wrong[1..])?);
+                    Ok(())
+                }
+
                 #[test]
                 fn test_version() {
                     if let Ok(kem) = Kem::new(Algorithm::$kem) {
@@ -115,491 +609,3913 @@ macro_rules! implement_kems {
 }
 
 implement_kems! {
-    ("bike") BikeL1: OQS_KEM_alg_bike_l1,
-    ("bike") BikeL3: OQS_KEM_alg_bike_l3,
-    ("classic_mceliece") ClassicMcEliece348864: OQS_KEM_alg_classic_mceliece_348864,
-    ("classic_mceliece") ClassicMcEliece348864f: OQS_KEM_alg_classic_mceliece_348864f,
-    ("classic_mceliece") ClassicMcEliece460896: OQS_KEM_alg_classic_mceliece_460896,
-    ("classic_mceliece") ClassicMcEliece460896f: OQS_KEM_alg_classic_mceliece_460896f,
-    ("classic_mceliece") ClassicMcEliece6688128: OQS_KEM_alg_classic_mceliece_6688128,
-    ("classic_mceliece") ClassicMcEliece6688128f: OQS_KEM_alg_classic_mceliece_6688128f,
-    ("classic_mceliece") ClassicMcEliece6960119: OQS_KEM_alg_classic_mceliece_6960119,
-    ("classic_mceliece") ClassicMcEliece6960119f: OQS_KEM_alg_classic_mceliece_6960119f,
-    ("classic_mceliece") ClassicMcEliece8192128: OQS_KEM_alg_classic_mceliece_8192128,
-    ("classic_mceliece") ClassicMcEliece8192128f: OQS_KEM_alg_classic_mceliece_8192128f,
-    ("hqc") Hqc128: OQS_KEM_alg_hqc_128,
-    ("hqc") Hqc192: OQS_KEM_alg_hqc_192,
-    ("hqc") Hqc256: OQS_KEM_alg_hqc_256,
-    ("kyber") Kyber512: OQS_KEM_alg_kyber_512,
-    ("kyber") Kyber768: OQS_KEM_alg_kyber_768,
-    ("kyber") Kyber1024: OQS_KEM_alg_kyber_1024,
-    ("kyber") Kyber512_90s: OQS_KEM_alg_kyber_512_90s,
-    ("kyber") Kyber768_90s: OQS_KEM_alg_kyber_768_90s,
-    ("kyber") Kyber1024_90s: OQS_KEM_alg_kyber_1024_90s,
-    ("ntru") NtruHps2048509: OQS_KEM_alg_ntru_hps2048509,
-    ("ntru") NtruHps2048677: OQS_KEM_alg_ntru_hps2048677,
-    ("ntru") NtruHps4096821: OQS_KEM_alg_ntru_hps4096821,
-    ("ntru") NtruHps40961229: OQS_KEM_alg_ntru_hps40961229,
-    ("ntru") NtruHrss701: OQS_KEM_alg_ntru_hrss701,
-    ("ntru") NtruHrss1373: OQS_KEM_alg_ntru_hrss1373,
-    ("ntruprime") NtruPrimeNtrulpr653: OQS_KEM_alg_ntruprime_ntrulpr653,
-    ("ntruprime") NtruPrimeNtrulpr761: OQS_KEM_alg_ntruprime_ntrulpr761,
-    ("ntruprime") NtruPrimeNtrulpr857: OQS_KEM_alg_ntruprime_ntrulpr857,
-    ("ntruprime") NtruPrimeNtrulpr1277: OQS_KEM_alg_ntruprime_ntrulpr1277,
-    ("ntruprime") NtruPrimeSntrup653: OQS_KEM_alg_ntruprime_sntrup653,
-    ("ntruprime") NtruPrimeSntrup761: OQS_KEM_alg_ntruprime_sntrup761,
-    ("ntruprime") NtruPrimeSntrup857: OQS_KEM_alg_ntruprime_sntrup857,
-    ("ntruprime") NtruPrimeSntrup1277: OQS_KEM_alg_ntruprime_sntrup1277,
-    ("saber") Lightsaber: OQS_KEM_alg_saber_lightsaber,
-    ("saber") Saber: OQS_KEM_alg_saber_saber,
-    ("saber") Firesaber: OQS_KEM_alg_saber_firesaber,
-    ("frodokem") FrodoKem640Aes: OQS_KEM_alg_frodokem_640_aes,
-    ("frodokem") FrodoKem640Shake: OQS_KEM_alg_frodokem_640_shake,
-    ("frodokem") FrodoKem976Aes: OQS_KEM_alg_frodokem_976_aes,
-    ("frodokem") FrodoKem976Shake: OQS_KEM_alg_frodokem_976_shake,
-    ("frodokem") FrodoKem1344Aes: OQS_KEM_alg_frodokem_1344_aes,
-    ("frodokem") FrodoKem1344Shake: OQS_KEM_alg_frodokem_1344_shake,
-    ("frodokem") FrodoKem640Aes1CCA: OQS_KEM_alg_frodokem_640_aes_1cca,
-    ("frodokem") FrodoKem640Shake1CCA: OQS_KEM_alg_frodokem_640_shake_1cca,
-    ("frodokem") FrodoKem976Aes1CCA: OQS_KEM_alg_frodokem_976_aes_1cca,
-    ("frodokem") FrodoKem976Shake1CCA: OQS_KEM_alg_frodokem_976_shake_1cca,
-    ("frodokem") FrodoKem1344Aes1CCA: OQS_KEM_alg_frodokem_1344_aes_1cca,
-    ("frodokem") FrodoKem1344Shake1CCA: OQS_KEM_alg_frodokem_1344_shake_1cca,
-    ("sidh") SidhP434: OQS_KEM_alg_sidh_p434,
-    ("sidh") SidhP503: OQS_KEM_alg_sidh_p503,
-    ("sidh") SidhP610: OQS_KEM_alg_sidh_p610,
-    ("sidh") SidhP751: OQS_KEM_alg_sidh_p751,
-    ("sidh") SidhP434Compressed: OQS_KEM_alg_sidh_p434_compressed,
-    ("sidh") SidhP503Compressed: OQS_KEM_alg_sidh_p503_compressed,
-    ("sidh") SidhP610Compressed: OQS_KEM_alg_sidh_p610_compressed,
-    ("sidh") SidhP751Compressed: OQS_KEM_alg_sidh_p751_compressed,
-    ("sike") SikeP434: OQS_KEM_alg_sike_p434,
-    ("sike") SikeP503: OQS_KEM_alg_sike_p503,
-    ("sike") SikeP610: OQS_KEM_alg_sike_p610,
-    ("sike") SikeP751: OQS_KEM_alg_sike_p751,
-    ("sike") SikeP434Compressed: OQS_KEM_alg_sike_p434_compressed,
-    ("sike") SikeP503Compressed: OQS_KEM_alg_sike_p503_compressed,
-    ("sike") SikeP610Compressed: OQS_KEM_alg_sike_p610_compressed,
-    ("sike") SikeP751Compressed: OQS_KEM_alg_sike_p751_compressed,
-    ("sike") SikeP434Compressed1CCA: OQS_KEM_alg_sike_p434_1cca_compressed,
-    ("sike") SikeP503Compressed1CCA: OQS_KEM_alg_sike_p503_1cca_compressed,
-    ("sike") SikeP610Compressed1CCA: OQS_KEM_alg_sike_p610_1cca_compressed,
-    ("sike") SikeP751Compressed1CCA: OQS_KEM_alg_sike_p751_1cca_compressed,
-    ("csidh") CsidhP512: OQS_KEM_alg_csidh_p512,
-    ("csidh") CsidhP1024: OQS_KEM_alg_csidh_p1024,
+    ("bike") BikeL1: OQS_KEM_alg_bike_l1: []: (1541, 5223, 1573, 32),
+    ("bike") BikeL3: OQS_KEM_alg_bike_l3: []: (3083, 10105, 3115, 32),
+    ("classic_mceliece") ClassicMcEliece348864: OQS_KEM_alg_classic_mceliece_348864: []: (261120, 6452, 128, 32),
+    ("classic_mceliece") ClassicMcEliece348864f: OQS_KEM_alg_classic_mceliece_348864f: []: (261120, 6452, 128, 32),
+    ("classic_mceliece") ClassicMcEliece460896: OQS_KEM_alg_classic_mceliece_460896: []: (524160, 13568, 188, 32),
+    ("classic_mceliece") ClassicMcEliece460896f: OQS_KEM_alg_classic_mceliece_460896f: []: (524160, 13568, 188, 32),
+    ("classic_mceliece") ClassicMcEliece6688128: OQS_KEM_alg_classic_mceliece_6688128: []: (1044992, 13892, 240, 32),
+    ("classic_mceliece") ClassicMcEliece6688128f: OQS_KEM_alg_classic_mceliece_6688128f: []: (1044992, 13892, 240, 32),
+    ("classic_mceliece") ClassicMcEliece6960119: OQS_KEM_alg_classic_mceliece_6960119: []: (1047319, 13908, 226, 32),
+    ("classic_mceliece") ClassicMcEliece6960119f: OQS_KEM_alg_classic_mceliece_6960119f: []: (1047319, 13908, 226, 32),
+    ("classic_mceliece") ClassicMcEliece8192128: OQS_KEM_alg_classic_mceliece_8192128: []: (1357824, 14080, 240, 32),
+    ("classic_mceliece") ClassicMcEliece8192128f: OQS_KEM_alg_classic_mceliece_8192128f: []: (1357824, 14080, 240, 32),
+    ("hqc") Hqc128: OQS_KEM_alg_hqc_128: []: (2249, 2289, 4481, 64),
+    ("hqc") Hqc192: OQS_KEM_alg_hqc_192: []: (4522, 4562, 9026, 64),
+    ("hqc") Hqc256: OQS_KEM_alg_hqc_256: []: (7245, 7285, 14469, 64),
+    ("kyber") Kyber512: OQS_KEM_alg_kyber_512: [118, 107]: (800, 1632, 768, 32),
+    ("kyber") Kyber768: OQS_KEM_alg_kyber_768: [183, 166]: (1184, 2400, 1088, 32),
+    ("kyber") Kyber1024: OQS_KEM_alg_kyber_1024: [256, 232]: (1568, 3168, 1568, 32),
+    ("kyber") Kyber512_90s: OQS_KEM_alg_kyber_512_90s: [118, 107]: (800, 1632, 768, 32),
+    ("kyber") Kyber768_90s: OQS_KEM_alg_kyber_768_90s: [183, 166]: (1184, 2400, 1088, 32),
+    ("kyber") Kyber1024_90s: OQS_KEM_alg_kyber_1024_90s: [256, 232]: (1568, 3168, 1568, 32),
+    ("ntru") NtruHps2048509: OQS_KEM_alg_ntru_hps2048509: [106, 96]: (699, 935, 699, 32),
+    ("ntru") NtruHps2048677: OQS_KEM_alg_ntru_hps2048677: [145, 131]: (930, 1234, 930, 32),
+    ("ntru") NtruHps4096821: OQS_KEM_alg_ntru_hps4096821: [179, 162]: (1230, 1590, 1230, 32),
+    ("ntru") NtruHps40961229: OQS_KEM_alg_ntru_hps40961229: []: (1842, 2366, 1842, 32),
+    ("ntru") NtruHrss701: OQS_KEM_alg_ntru_hrss701: [136, 123]: (1138, 1450, 1138, 32),
+    ("ntru") NtruHrss1373: OQS_KEM_alg_ntru_hrss1373: []: (2401, 2983, 2401, 32),
+    ("ntruprime") NtruPrimeNtrulpr653: OQS_KEM_alg_ntruprime_ntrulpr653: [130, 118]: (897, 1125, 1025, 32),
+    ("ntruprime") NtruPrimeNtrulpr761: OQS_KEM_alg_ntruprime_ntrulpr761: [155, 140]: (1039, 1294, 1167, 32),
+    ("ntruprime") NtruPrimeNtrulpr857: OQS_KEM_alg_ntruprime_ntrulpr857: [176, 160]: (1184, 1463, 1312, 32),
+    ("ntruprime") NtruPrimeNtrulpr1277: OQS_KEM_alg_ntruprime_ntrulpr1277: []: (1701, 2036, 1847, 32),
+    ("ntruprime") NtruPrimeSntrup653: OQS_KEM_alg_ntruprime_sntrup653: [129, 117]: (994, 1518, 897, 32),
+    ("ntruprime") NtruPrimeSntrup761: OQS_KEM_alg_ntruprime_sntrup761: [153, 139]: (1158, 1763, 1039, 32),
+    ("ntruprime") NtruPrimeSntrup857: OQS_KEM_alg_ntruprime_sntrup857: [175, 159]: (1322, 1999, 1184, 32),
+    ("ntruprime") NtruPrimeSntrup1277: OQS_KEM_alg_ntruprime_sntrup1277: []: (1847, 2720, 1683, 32),
+    ("saber") Lightsaber: OQS_KEM_alg_saber_lightsaber: [118, 107]: (672, 1568, 736, 32),
+    ("saber") Saber: OQS_KEM_alg_saber_saber: [189, 172]: (992, 2304, 1088, 32),
+    ("saber") Firesaber: OQS_KEM_alg_saber_firesaber: [260, 236]: (1312, 3040, 1472, 32),
+    ("frodokem") FrodoKem640Aes: OQS_KEM_alg_frodokem_640_aes: []: (9616, 19888, 9720, 16),
+    ("frodokem") FrodoKem640Shake: OQS_KEM_alg_frodokem_640_shake: []: (9616, 19888, 9720, 16),
+    ("frodokem") FrodoKem976Aes: OQS_KEM_alg_frodokem_976_aes: []: (15632, 31296, 15744, 24),
+    ("frodokem") FrodoKem976Shake: OQS_KEM_alg_frodokem_976_shake: []: (15632, 31296, 15744, 24),
+    ("frodokem") FrodoKem1344Aes: OQS_KEM_alg_frodokem_1344_aes: []: (21520, 43088, 21632, 32),
+    ("frodokem") FrodoKem1344Shake: OQS_KEM_alg_frodokem_1344_shake: []: (21520, 43088, 21632, 32),
+    ("frodokem") FrodoKem640Aes1CCA: OQS_KEM_alg_frodokem_640_aes_1cca: []: (9616, 19888, 9720, 16),
+    ("frodokem") FrodoKem640Shake1CCA: OQS_KEM_alg_frodokem_640_shake_1cca: []: (9616, 19888, 9720, 16),
+    ("frodokem") FrodoKem976Aes1CCA: OQS_KEM_alg_frodokem_976_aes_1cca: []: (15632, 31296, 15744, 24),
+    ("frodokem") FrodoKem976Shake1CCA: OQS_KEM_alg_frodokem_976_shake_1cca: []: (15632, 31296, 15744, 24),
+    ("frodokem") FrodoKem1344Aes1CCA: OQS_KEM_alg_frodokem_1344_aes_1cca: []: (21520, 43088, 21632, 32),
+    ("frodokem") FrodoKem1344Shake1CCA: OQS_KEM_alg_frodokem_1344_shake_1cca: []: (21520, 43088, 21632, 32),
+    ("sidh") SidhP434: OQS_KEM_alg_sidh_p434: []: (330, 28, 330, 110),
+    ("sidh") SidhP503: OQS_KEM_alg_sidh_p503: []: (378, 32, 378, 126),
+    ("sidh") SidhP610: OQS_KEM_alg_sidh_p610: []: (462, 39, 462, 154),
+    ("sidh") SidhP751: OQS_KEM_alg_sidh_p751: []: (564, 48, 564, 188),
+    ("sidh") SidhP434Compressed: OQS_KEM_alg_sidh_p434_compressed: []: (197, 28, 197, 110),
+    ("sidh") SidhP503Compressed: OQS_KEM_alg_sidh_p503_compressed: []: (225, 32, 225, 126),
+    ("sidh") SidhP610Compressed: OQS_KEM_alg_sidh_p610_compressed: []: (274, 39, 274, 154),
+    ("sidh") SidhP751Compressed: OQS_KEM_alg_sidh_p751_compressed: []: (335, 48, 335, 188),
+    ("sike") SikeP434: OQS_KEM_alg_sike_p434: []: (330, 374, 346, 16),
+    ("sike") SikeP503: OQS_KEM_alg_sike_p503: []: (378, 434, 402, 24),
+    ("sike") SikeP610: OQS_KEM_alg_sike_p610: []: (462, 524, 486, 24),
+    ("sike") SikeP751: OQS_KEM_alg_sike_p751: []: (564, 644, 596, 32),
+    ("sike") SikeP434Compressed: OQS_KEM_alg_sike_p434_compressed: []: (197, 350, 236, 16),
+    ("sike") SikeP503Compressed: OQS_KEM_alg_sike_p503_compressed: []: (225, 407, 280, 24),
+    ("sike") SikeP610Compressed: OQS_KEM_alg_sike_p610_compressed: []: (274, 491, 336, 24),
+    ("sike") SikeP751Compressed: OQS_KEM_alg_sike_p751_compressed: []: (335, 602, 410, 32),
+    ("sike") SikeP434Compressed1CCA: OQS_KEM_alg_sike_p434_1cca_compressed: []: (197, 350, 236, 16),
+    ("sike") SikeP503Compressed1CCA: OQS_KEM_alg_sike_p503_1cca_compressed: []: (225, 407, 280, 24),
+    ("sike") SikeP610Compressed1CCA: OQS_KEM_alg_sike_p610_1cca_compressed: []: (274, 491, 336, 24),
+    ("sike") SikeP751Compressed1CCA: OQS_KEM_alg_sike_p751_1cca_compressed: []: (335, 602, 410, 32),
+    ("csidh") CsidhP512: OQS_KEM_alg_csidh_p512: []: (64, 37, 64, 64),
+    ("csidh") CsidhP1024: OQS_KEM_alg_csidh_p1024: []: (128, 65, 128, 128),
 }
 
-impl Algorithm {
-    /// Returns true if this algorithm is enabled in the linked version
-    /// of liboqs
-    pub fn is_enabled(self) -> bool {
-        unsafe { ffi::OQS_KEM_alg_is_enabled(algorithm_to_id(self)) == 1 }
-    }
+/// Security strength estimate, see [`Algorithm::estimated_security_bits`]
+///
+/// Optional support for `serde` if that feature is enabled.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SecurityBits {
+    /// Estimated bits of security against classical attackers
+    pub classical: u32,
+    /// Estimated bits of security against quantum attackers
+    pub quantum: u32,
+}
 
-    /// Provides a pointer to the id of the algorithm
-    ///
-    /// For use with the FFI api methods
-    pub fn to_id(self) -> *const libc::c_char {
-        algorithm_to_id(self)
+impl SecurityBits {
+    /// Read the `[classical, quantum]` column of the KEM table, which is empty without an estimate
+    fn from_table(bits: &[u32]) -> Option<SecurityBits> {
+        match *bits {
+            [classical, quantum] => Some(SecurityBits { classical, quantum }),
+            _ => None,
+        }
     }
+}
 
-    /// Returns the algorithm's name as a static Rust string.
-    ///
-    /// This is the same as the `to_id`, but as a safe Rust string.
-    pub fn name(&self) -> &'static str {
-        // SAFETY: The id from ffi must be a proper null terminated C string
-        let id = unsafe { CStr::from_ptr(self.to_id()) };
-        id.to_str().expect("OQS algorithm names must be UTF-8")
-    }
+/// Sizes in bytes of the objects of an algorithm, see [`Algorithm::sizes`]
+///
+/// Optional support for `serde` if that feature is enabled.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KemSizes {
+    /// Length of a public key
+    pub public_key: usize,
+    /// Length of a secret key
+    pub secret_key: usize,
+    /// Length of a ciphertext
+    pub ciphertext: usize,
+    /// Length of a shared secret
+    pub shared_secret: usize,
 }
 
-#[cfg(feature = "std")]
-impl std::fmt::Display for Algorithm {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.name().fmt(f)
+/// Families of KEM algorithms
+///
+/// Each family corresponds to the crate feature that enables it.
+///
+/// Optional support for `serde` if that feature is enabled.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[allow(missing_docs)]
+pub enum Family {
+    Bike,
+    ClassicMcEliece,
+    Csidh,
+    FrodoKem,
+    Hqc,
+    Kyber,
+    Ntru,
+    NtruPrime,
+    Saber,
+    Sidh,
+    Sike,
+}
+
+impl Family {
+    /// Map the feature name used in `implement_kems!` to the family
+    fn from_feature(feature: &str) -> Family {
+        match feature {
+            "bike" => Family::Bike,
+            "classic_mceliece" => Family::ClassicMcEliece,
+            "csidh" => Family::Csidh,
+            "frodokem" => Family::FrodoKem,
+            "hqc" => Family::Hqc,
+            "kyber" => Family::Kyber,
+            "ntru" => Family::Ntru,
+            "ntruprime" => Family::NtruPrime,
+            "saber" => Family::Saber,
+            "sidh" => Family::Sidh,
+            "sike" => Family::Sike,
+            _ => unreachable!("unknown KEM family feature {}", feature),
+        }
     }
 }
 
-/// KEM algorithm
+/// Rough classification of the bandwidth cost of an algorithm
 ///
-/// # Example
-/// ```rust
-/// # if !cfg!(feature = "kyber") { return; }
-/// use oqs;
-/// oqs::init();
-/// let kem = oqs::kem::Kem::new(oqs::kem::Algorithm::Kyber512).unwrap();
-/// let (pk, sk) = kem.keypair().unwrap();
-/// let (ct, ss) = kem.encapsulate(&pk).unwrap();
-/// let ss2 = kem.decapsulate(&sk, &ct).unwrap();
-/// assert_eq!(ss, ss2);
-/// ```
-pub struct Kem {
-    algorithm: Algorithm,
-    kem: NonNull<ffi::OQS_KEM>,
+/// Based on the published sizes of public keys and ciphertexts,
+/// as both typically go over the wire once per key exchange.
+/// This is advisory: it is derived from the static [`Algorithm::sizes`], not a measurement.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SizeClass {
+    /// Public key and ciphertext together take at most 4 KiB
+    Compact,
+    /// Public key and ciphertext together take at most 16 KiB
+    Medium,
+    /// Public key and ciphertext together take more than 16 KiB
+    Large,
+    /// The public key alone takes more than 64 KiB
+    HugeKeys,
 }
 
-unsafe impl Sync for Kem {}
-unsafe impl Send for Kem {}
-
-impl Drop for Kem {
-    fn drop(&mut self) {
-        unsafe { ffi::OQS_KEM_free(self.kem.as_ptr()) };
+impl SizeClass {
+    /// Classify an algorithm by its public key and ciphertext lengths
+    const fn from_lengths(public_key: usize, ciphertext: usize) -> SizeClass {
+        if public_key > 64 * 1024 {
+            SizeClass::HugeKeys
+        } else if public_key + ciphertext <= 4 * 1024 {
+            SizeClass::Compact
+        } else if public_key + ciphertext <= 16 * 1024 {
+            SizeClass::Medium
+        } else {
+            SizeClass::Large
+        }
     }
 }
 
-impl core::convert::TryFrom<Algorithm> for Kem {
-    type Error = crate::Error;
-    fn try_from(alg: Algorithm) -> Result<Kem> {
-        Kem::new(alg)
+/// Security notion offered by a KEM operation
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SecurityNotion {
+    /// Indistinguishability under adaptive chosen-ciphertext attack
+    IndCca,
+    /// Indistinguishability under chosen-plaintext attack only
+    ///
+    /// Such a ciphertext must only be used with ephemeral keys.
+    IndCpa,
+    /// The operation is not provided by the algorithm
+    Unsupported,
+}
+
+/// How the length of a ciphertext compares to the expected one,
+/// see [`Kem::diagnose_ciphertext`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum CiphertextDiagnosis {
+    /// The ciphertext has the expected length
+    Exact,
+    /// The ciphertext is this many bytes too short
+    TooShort(usize),
+    /// The ciphertext is this many bytes too long
+    TooLong(usize),
+}
+
+impl core::fmt::Display for CiphertextDiagnosis {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CiphertextDiagnosis::Exact => write!(f, "ciphertext has the expected length"),
+            CiphertextDiagnosis::TooShort(by) => write!(f, "ciphertext is {} bytes too short", by),
+            CiphertextDiagnosis::TooLong(by) => write!(f, "ciphertext is {} bytes too long", by),
+        }
     }
 }
 
-impl Kem {
-    /// Construct a new algorithm
-    pub fn new(algorithm: Algorithm) -> Result<Self> {
-        let kem = unsafe { ffi::OQS_KEM_new(algorithm_to_id(algorithm)) };
-        NonNull::new(kem).map_or_else(
-            || Err(Error::AlgorithmDisabled),
-            |kem| Ok(Self { algorithm, kem }),
-        )
+/// Set of operations a [`Kem`] provides, see [`Kem::available_operations`]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct OperationFlags(u8);
+
+impl OperationFlags {
+    /// [`Kem::keypair`]
+    pub const KEYPAIR: OperationFlags = OperationFlags(1 << 0);
+    /// [`Kem::encapsulate`]
+    pub const ENCAPSULATE: OperationFlags = OperationFlags(1 << 1);
+    /// [`Kem::decapsulate`]
+    pub const DECAPSULATE: OperationFlags = OperationFlags(1 << 2);
+    /// [`Kem::async_encapsulate`]
+    pub const ASYNC_ENCAPSULATE: OperationFlags = OperationFlags(1 << 3);
+    /// [`Kem::encapsulate_ciphertext`] and [`Kem::encapsulate_shared_secret`]
+    pub const SPLIT_ENCAPSULATION: OperationFlags = OperationFlags(1 << 4);
+
+    /// The empty set
+    pub const fn empty() -> OperationFlags {
+        OperationFlags(0)
     }
 
-    /// Get the algorithm used by this `Kem`
-    pub fn algorithm(&self) -> Algorithm {
-        self.algorithm
+    /// The raw bits of this set
+    pub const fn bits(self) -> u8 {
+        self.0
     }
 
-    /// Get the version of the implementation
-    pub fn version(&self) -> &'static str {
-        let kem = unsafe { self.kem.as_ref() };
-        // SAFETY: The alg_version from ffi must be a proper null terminated C string
-        let cstr = unsafe { CStr::from_ptr(kem.alg_version) };
-        cstr.to_str()
-            .expect("Algorithm version strings must be UTF-8")
+    /// Returns true if all operations in `other` are in this set
+    pub const fn contains(self, other: OperationFlags) -> bool {
+        self.0 & other.0 == other.0
     }
+}
 
-    /// Get the claimed nist level
-    pub fn claimed_nist_level(&self) -> u8 {
-        let kem = unsafe { self.kem.as_ref() };
-        kem.claimed_nist_level
+impl core::ops::BitOr for OperationFlags {
+    type Output = OperationFlags;
+    fn bitor(self, other: OperationFlags) -> OperationFlags {
+        OperationFlags(self.0 | other.0)
     }
+}
 
-    /// Is the algorithm ind_cca secure
-    pub fn is_ind_cca(&self) -> bool {
-        let kem = unsafe { self.kem.as_ref() };
-        kem.ind_cca
+impl core::ops::BitOrAssign for OperationFlags {
+    fn bitor_assign(&mut self, other: OperationFlags) {
+        self.0 |= other.0;
+    }
+}
+
+/// Returns all algorithms known to this crate, including disabled ones
+pub fn all() -> &'static [Algorithm] {
+    ALGORITHMS
+}
+
+/// Returns a curated list of algorithms that are safe defaults
+///
+/// The selection criteria are:
+///
+/// * the algorithm is standardized by NIST: Kyber became ML-KEM (FIPS 203);
+/// * no practical attack on it is known;
+/// * it is the standard variant (the `90s` variants were not standardized).
+///
+/// Isogeny-based schemes (SIDH and SIKE) are excluded because they are broken by
+/// the key-recovery attack of Castryck and Decru. CSIDH is excluded because its
+/// security level is not settled. The list is ordered by increasing security level
+/// and may contain algorithms that are disabled in the linked liboqs.
+pub fn recommended() -> &'static [Algorithm] {
+    &[
+        Algorithm::Kyber512,
+        Algorithm::Kyber768,
+        Algorithm::Kyber1024,
+    ]
+}
+
+/// Returns the enabled algorithms grouped by family
+///
+/// Only algorithms that are enabled in the linked liboqs are included,
+/// as the NIST level is obtained by constructing a [`Kem`].
+/// Within a family, the algorithms are sorted by claimed NIST level,
+/// strongest first; algorithms with the same level keep their declaration order.
+pub fn algorithms_by_family() -> BTreeMap<Family, Vec<Algorithm>> {
+    let mut families: BTreeMap<Family, Vec<(u8, Algorithm)>> = BTreeMap::new();
+    for &algorithm in all() {
+        if let Ok(kem) = Kem::new(algorithm) {
+            families
+                .entry(algorithm.family())
+                .or_default()
+                .push((kem.claimed_nist_level(), algorithm));
+        }
+    }
+    families
+        .into_iter()
+        .map(|(family, mut algorithms)| {
+            algorithms.sort_by_key(|&(level, _)| core::cmp::Reverse(level));
+            (family, algorithms.into_iter().map(|(_, alg)| alg).collect())
+        })
+        .collect()
+}
+
+/// Group `algorithms` by the length of their shared secrets
+///
+/// A negotiation set with fixed-size framing needs all its algorithms in a single
+/// group. The map is sorted by length; within a group, the algorithms keep the order
+/// of `algorithms`. This uses [`Algorithm::sizes`], so it covers disabled
+/// algorithms too and constructs no [`Kem`].
+pub fn shared_secret_lengths(algorithms: &[Algorithm]) -> BTreeMap<usize, Vec<Algorithm>> {
+    let mut lengths: BTreeMap<usize, Vec<Algorithm>> = BTreeMap::new();
+    for &algorithm in algorithms {
+        lengths
+            .entry(algorithm.sizes().shared_secret)
+            .or_default()
+            .push(algorithm);
+    }
+    lengths
+}
+
+/// All algorithms with their [ciphertext expansion](Algorithm::ciphertext_expansion),
+/// smallest first
+///
+/// Algorithms with the same expansion keep their declaration order. The ranking
+/// ignores public key sizes: Classic McEliece has some of the smallest ciphertexts
+/// but by far the largest public keys.
+pub fn ranked_by_expansion() -> Vec<(Algorithm, f64)> {
+    let mut ranked: Vec<(Algorithm, f64)> = all()
+        .iter()
+        .map(|&alg| (alg, alg.ciphertext_expansion()))
+        .collect();
+    ranked.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+    ranked
+}
+
+/// The enabled algorithms whose ciphertexts and public keys fit in a byte budget
+///
+/// Returns the algorithms with a ciphertext of at most `max_ct` bytes and
+/// a public key of at most `max_pk` bytes, sorted by ciphertext size, smallest first.
+pub fn algorithms_under(max_ct: usize, max_pk: usize) -> Vec<Algorithm> {
+    let mut algorithms: Vec<(usize, Algorithm)> = all()
+        .iter()
+        .filter_map(|&algorithm| {
+            let kem = Kem::new(algorithm).ok()?;
+            let ct_len = kem.length_ciphertext();
+            if ct_len <= max_ct && kem.length_public_key() <= max_pk {
+                Some((ct_len, algorithm))
+            } else {
+                None
+            }
+        })
+        .collect();
+    algorithms.sort_by_key(|&(ct_len, _)| ct_len);
+    algorithms.into_iter().map(|(_, alg)| alg).collect()
+}
+
+/// The algorithms that are enabled in the linked liboqs, in declaration order
+pub fn enabled_algorithms() -> impl Iterator<Item = Algorithm> {
+    all().iter().copied().filter(|alg| alg.is_enabled())
+}
+
+/// A ready-to-use `Kem` for every enabled algorithm, constructed lazily
+///
+/// Each `Kem` is constructed when the iterator reaches it and initialized if
+/// its algorithm needs it, see [`Algorithm::requires_init`]. Dropping an
+/// initialized `Kem` releases its initialization again, so keep no more of
+/// them alive than needed.
+pub fn iter_enabled_kems() -> impl Iterator<Item = Result<Kem>> {
+    enabled_algorithms().map(|algorithm| {
+        let kem = Kem::new(algorithm)?;
+        if algorithm.requires_init() {
+            kem.init()?;
+        }
+        Ok(kem)
+    })
+}
+
+/// The enabled algorithms that are not known to be broken
+///
+/// Returns the algorithms that are enabled in the linked liboqs and for which
+/// [`Algorithm::is_cryptographically_broken`] is false: the list most applications
+/// should offer. It is sorted by claimed NIST level, weakest first, then by family;
+/// algorithms with the same level and family keep their declaration order.
+pub fn safe_enabled() -> Vec<Algorithm> {
+    let mut algorithms: Vec<(u8, Family, Algorithm)> = all()
+        .iter()
+        .filter(|algorithm| !algorithm.is_cryptographically_broken())
+        .filter_map(|&algorithm| {
+            let kem = Kem::new(algorithm).ok()?;
+            Some((kem.claimed_nist_level(), algorithm.family(), algorithm))
+        })
+        .collect();
+    algorithms.sort_by_key(|&(level, family, _)| (level, family));
+    algorithms.into_iter().map(|(_, _, alg)| alg).collect()
+}
+
+/// The enabled algorithms that NIST selected for standardization and are not broken
+///
+/// [`safe_enabled`] restricted to [`Algorithm::is_nist_selected`], in the same
+/// order. None of them is an implementation of a published NIST standard, see
+/// [`Algorithm::is_nist_selected`].
+pub fn nist_selected_enabled() -> Vec<Algorithm> {
+    safe_enabled()
+        .into_iter()
+        .filter(Algorithm::is_nist_selected)
+        .collect()
+}
+
+impl Algorithm {
+    /// Returns true if this algorithm is enabled in the linked version
+    /// of liboqs
+    pub fn is_enabled(self) -> bool {
+        unsafe { ffi::OQS_KEM_alg_is_enabled(algorithm_to_id(self)) == 1 }
+    }
+
+    /// Provides a pointer to the id of the algorithm
+    ///
+    /// For use with the FFI api methods
+    pub fn to_id(self) -> *const libc::c_char {
+        algorithm_to_id(self)
+    }
+
+    /// Returns the id of the algorithm as a C string
+    ///
+    /// For passing the id to other C APIs without the raw pointer of
+    /// [`Algorithm::to_id`] or the UTF-8 check of [`Algorithm::name`].
+    /// Without the `std` feature, this is the `CStr` of the `cstr_core` crate.
+    pub fn as_cstr(&self) -> &'static CStr {
+        CStr::from_bytes_with_nul(algorithm_id_bytes(*self))
+            .expect("OQS algorithm ids must be nul-terminated")
+    }
+
+    /// Returns the algorithm's name as a static Rust string.
+    ///
+    /// This is the same as the `to_id`, but as a safe Rust string.
+    pub fn name(&self) -> &'static str {
+        // SAFETY: The id from ffi must be a proper null terminated C string
+        let id = unsafe { CStr::from_ptr(self.to_id()) };
+        id.to_str().expect("OQS algorithm names must be UTF-8")
+    }
+
+    /// Look up an algorithm by a loosely written name, e.g. from a config file
+    ///
+    /// Case and the separators `-`, `_` and space are ignored. Besides the liboqs
+    /// name (see [`Algorithm::name`]), this accepts the name of the enum variant and
+    /// the ML-KEM name of [`Algorithm::ml_kem_equivalent`], so `"kyber512"`,
+    /// `"KYBER-512"` and `"ML-KEM-512"` all give [`Algorithm::Kyber512`].
+    /// Note that Kyber does not interoperate with ML-KEM. Use the strict
+    /// [`str::parse`] for names exchanged between programs.
+    pub fn parse_relaxed(name: &str) -> Option<Algorithm> {
+        fn normalized(name: &str) -> impl Iterator<Item = char> + '_ {
+            name.chars()
+                .filter(|c| !matches!(c, '-' | '_' | ' '))
+                .map(|c| c.to_ascii_lowercase())
+        }
+        let matches = |candidate: &str| normalized(candidate).eq(normalized(name));
+        all().iter().copied().find(|algorithm| {
+            matches(algorithm.name())
+                || matches(algorithm.variant_name())
+                || algorithm.ml_kem_equivalent().is_some_and(matches)
+        })
+    }
+
+    /// Returns the rough bandwidth cost of this algorithm
+    ///
+    /// See [`SizeClass`]; this is advisory and does not require the algorithm to be enabled.
+    pub const fn size_class(&self) -> SizeClass {
+        let sizes = self.sizes();
+        SizeClass::from_lengths(sizes.public_key, sizes.ciphertext)
+    }
+
+    /// Returns true if this algorithm is a non-interactive key exchange
+    ///
+    /// With a NIKE, both parties publish a public key and each combines its own
+    /// secret key with the other's public key. liboqs only exposes CSIDH through
+    /// its KEM interface, whose encapsulation generates a fresh ephemeral key every
+    /// time, and offers no access to the group action itself, so this crate
+    /// cannot provide the non-interactive use. CSIDH is only built with the
+    /// `csidh` feature, which is not part of `kems`.
+    pub fn is_nike(&self) -> bool {
+        matches!(self, Algorithm::CsidhP512 | Algorithm::CsidhP1024)
+    }
+
+    /// Returns the FIPS 203 (ML-KEM) name of the standardized version of this algorithm
+    ///
+    /// ML-KEM is the standardized form of Kyber, but the two are not interoperable:
+    /// FIPS 203 dropped the hashing of the encapsulated message and derives the shared
+    /// secret differently, so Kyber keys and ciphertexts cannot be used with ML-KEM.
+    /// The security levels do correspond. The `90s` variants have no ML-KEM counterpart.
+    ///
+    /// The liboqs this crate links predates FIPS 203 and does not provide ML-KEM itself.
+    pub fn ml_kem_equivalent(&self) -> Option<&'static str> {
+        match self {
+            Algorithm::Kyber512 => Some("ML-KEM-512"),
+            Algorithm::Kyber768 => Some("ML-KEM-768"),
+            Algorithm::Kyber1024 => Some("ML-KEM-1024"),
+            _ => None,
+        }
+    }
+
+    /// Returns true if liboqs builds this algorithm on OpenSSL when available
+    ///
+    /// These are the `90s` Kyber variants and the AES FrodoKEM variants, which
+    /// use AES and SHA-2. The liboqs bundled with this crate falls back to its own
+    /// AES and SHA-2 without the `openssl` feature, but a liboqs built otherwise
+    /// may leave them disabled; [`Kem::new`] then returns [`Error::MissingDependency`].
+    pub fn requires_openssl(&self) -> bool {
+        matches!(
+            self,
+            Algorithm::Kyber512_90s
+                | Algorithm::Kyber768_90s
+                | Algorithm::Kyber1024_90s
+                | Algorithm::FrodoKem640Aes
+                | Algorithm::FrodoKem976Aes
+                | Algorithm::FrodoKem1344Aes
+        )
+    }
+
+    /// Ciphertext bytes sent per byte of shared secret
+    ///
+    /// Computed from [`Algorithm::sizes`], so it works for disabled algorithms too.
+    /// This only covers what the encapsulating side sends; the public key usually
+    /// has to be sent as well, see [`KemSizes::public_key`].
+    pub fn ciphertext_expansion(&self) -> f64 {
+        let sizes = self.sizes();
+        sizes.ciphertext as f64 / sizes.shared_secret as f64
+    }
+
+    /// Returns true if NIST selected this algorithm for standardization
+    ///
+    /// This is a static classification reflecting the status as of March 2025:
+    /// Kyber512, Kyber768 and Kyber1024 were standardized, with changes, as
+    /// ML-KEM-512, ML-KEM-768 and ML-KEM-1024 in FIPS 203 (August 2024), see
+    /// [`Algorithm::ml_kem_equivalent`], and HQC was selected in March 2025 with
+    /// no standard published yet. The `90s` variants and all other algorithms
+    /// were not selected.
+    ///
+    /// **This does not mean FIPS 203 conformance.** The linked liboqs implements
+    /// Kyber as submitted to the third round, which does not interoperate with
+    /// ML-KEM, so no algorithm of this crate implements a published NIST standard.
+    pub fn is_nist_selected(&self) -> bool {
+        self.ml_kem_equivalent().is_some() || self.family() == Family::Hqc
+    }
+
+    /// Returns true if this algorithm is broken by a known practical attack
+    ///
+    /// This is the case for all SIDH and SIKE variants, whose secret keys can be
+    /// recovered efficiently: W. Castryck and T. Decru, "An efficient key recovery
+    /// attack on SIDH", Eurocrypt 2023, <https://eprint.iacr.org/2022/975>.
+    /// They remain available for research and experiments, such as KEMTLS,
+    /// but must not be used to protect real data.
+    pub fn is_cryptographically_broken(&self) -> bool {
+        matches!(self.family(), Family::Sidh | Family::Sike)
+    }
+
+    /// Does the algorithm need [`Kem::init`] before encapsulating or decapsulating
+    ///
+    /// True for SIDH and SIKE, which use precomputed tables. Without them the
+    /// result would be wrong, so the `Kem` operations return [`Error::NotInitialized`].
+    pub fn requires_init(&self) -> bool {
+        matches!(self.family(), Family::Sidh | Family::Sike)
+    }
+}
+
+impl core::str::FromStr for Algorithm {
+    type Err = Error;
+
+    /// Parse the exact liboqs name of an algorithm, see [`Algorithm::name`]
+    ///
+    /// Returns [`Error::InvalidFormat`] for any other string; see
+    /// [`Algorithm::parse_relaxed`] for a lenient alternative.
+    fn from_str(name: &str) -> Result<Algorithm> {
+        all()
+            .iter()
+            .copied()
+            .find(|algorithm| algorithm.name() == name)
+            .ok_or(Error::InvalidFormat)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.name().fmt(f)
+    }
+}
+
+/// Length of a SHA-256 output, the pseudorandom key of HKDF-SHA256
+#[cfg(feature = "hkdf")]
+const HKDF_HASH_LEN: usize = 32;
+
+/// HKDF-Extract (RFC 5869) with SHA-256
+///
+/// An empty `salt` is the same as `HKDF_HASH_LEN` zero bytes, as RFC 5869 specifies.
+#[cfg(feature = "hkdf")]
+fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> zeroize::Zeroizing<[u8; HKDF_HASH_LEN]> {
+    use hmac::{Hmac, Mac};
+
+    let mut mac =
+        Hmac::<sha2::Sha256>::new_from_slice(salt).expect("HMAC accepts keys of any length");
+    mac.update(ikm);
+    let mut prk = zeroize::Zeroizing::new([0u8; HKDF_HASH_LEN]);
+    prk.copy_from_slice(&mac.finalize().into_bytes()[..]);
+    prk
+}
+
+/// HKDF-Expand (RFC 5869) with SHA-256 of `prk` and `info`, filling `okm`
+///
+/// The intermediate blocks are zeroized.
+///
+/// # Panics
+/// If `okm` is longer than 255 * 32 bytes, the maximum HKDF-Expand output length.
+#[cfg(feature = "hkdf")]
+fn hkdf_expand(prk: &[u8], info: &[u8], okm: &mut [u8]) {
+    use hmac::{Hmac, Mac};
+
+    assert!(
+        okm.len() <= 255 * HKDF_HASH_LEN,
+        "HKDF-Expand output is limited to {} bytes",
+        255 * HKDF_HASH_LEN
+    );
+    let mut block = zeroize::Zeroizing::new([0u8; HKDF_HASH_LEN]);
+    for (counter, chunk) in (1u8..).zip(okm.chunks_mut(HKDF_HASH_LEN)) {
+        let mut mac =
+            Hmac::<sha2::Sha256>::new_from_slice(prk).expect("HMAC accepts keys of any length");
+        if counter > 1 {
+            mac.update(&block[..]);
+        }
+        mac.update(info);
+        mac.update(&[counter]);
+        block.copy_from_slice(&mac.finalize().into_bytes()[..]);
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+}
+
+/// Check that `buf` has exactly the `expected` length
+fn check_length(expected: usize, buf: &[u8]) -> Result<()> {
+    if buf.len() != expected {
+        Err(Error::InvalidLength {
+            expected,
+            got: buf.len(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// KEM algorithm
+///
+/// # Example
+/// ```rust
+/// # if !cfg!(feature = "kyber") { return; }
+/// use oqs;
+/// oqs::init();
+/// let kem = oqs::kem::Kem::new(oqs::kem::Algorithm::Kyber512).unwrap();
+/// let (pk, sk) = kem.keypair().unwrap();
+/// let (ct, ss) = kem.encapsulate(&pk).unwrap();
+/// let ss2 = kem.decapsulate(&sk, &ct).unwrap();
+/// assert_eq!(ss, ss2);
+/// ```
+///
+/// # Thread safety
+///
+/// A `Kem` can be shared between threads, and [`Kem::keypair`], [`Kem::encapsulate`]
+/// and [`Kem::decapsulate`] can be called on it concurrently:
+///
+/// * the `OQS_KEM` behind a `Kem` is only read after construction;
+/// * the liboqs implementations of these operations only use their arguments
+///   and the random number generator. The system and OpenSSL generators are
+///   thread-safe, and a custom generator from [`crate::rand`] sits behind a lock;
+/// * the reference count behind [`Kem::init`] and [`Kem::deinit`] is protected by a lock.
+///
+/// The asynchronous operations of this fork, [`Kem::keypair_async`] and
+/// [`Kem::async_encapsulate`], draw from precomputed state shared by the whole
+/// process instead. With the `std` feature they are serialized through a global lock;
+/// without it, the caller must make sure they do not run concurrently.
+///
+/// The global state built by [`Kem::init`] is only kept alive by initialized `Kem`s.
+/// Make sure a `Kem` that is used from several threads is initialized, so that the
+/// state is not freed by another thread in the middle of an operation.
+pub struct Kem {
+    algorithm: Algorithm,
+    kem: NonNull<ffi::OQS_KEM>,
+    /// Whether this `Kem` holds a reference on the initialized algorithm state
+    initialized: AtomicBool,
+    /// Fingerprints of the secret keys used for decapsulation, if reuse protection is on
+    #[cfg(feature = "std")]
+    used_secret_keys: Option<std::sync::Mutex<alloc::collections::BTreeSet<u64>>>,
+    /// Number of rejections seen by [`Kem::decapsulate_and_confirm`]
+    #[cfg(feature = "metrics")]
+    rejections: core::sync::atomic::AtomicU64,
+    /// The NUMA node to place output buffers on
+    #[cfg(feature = "numa")]
+    numa_node: Option<u32>,
+}
+
+unsafe impl Sync for Kem {}
+unsafe impl Send for Kem {}
+
+impl Drop for Kem {
+    fn drop(&mut self) {
+        #[cfg(feature = "std")]
+        if *self.initialized.get_mut() {
+            // Nothing sensible can be done about a failure here
+            let _ = release_init(self.algorithm);
+        }
+        unsafe { ffi::OQS_KEM_free(self.kem.as_ptr()) };
+    }
+}
+
+/// The error for `algorithm` being disabled, see [`Kem::new`]
+fn disabled_error(algorithm: Algorithm) -> Error {
+    let family_compiled = match algorithm.family() {
+        Family::FrodoKem => crate::features::HAS_FRODOKEM,
+        Family::Kyber => crate::features::HAS_KYBER,
+        _ => false,
+    };
+    if algorithm.requires_openssl() && family_compiled && !cfg!(feature = "openssl") {
+        Error::MissingDependency("OpenSSL")
+    } else {
+        Error::AlgorithmDisabled
+    }
+}
+
+/// Number of initialized `Kem`s, per algorithm
+///
+/// liboqs keeps the state built by `OQS_KEM_init` (the SIKE precomputation tables)
+/// in process-global memory, and `OQS_KEM_deinit` frees all of it at once.
+#[cfg(feature = "std")]
+static INIT_REFS: std::sync::Mutex<BTreeMap<Algorithm, usize>> =
+    std::sync::Mutex::new(BTreeMap::new());
+
+#[cfg(feature = "std")]
+fn init_refs() -> std::sync::MutexGuard<'static, BTreeMap<Algorithm, usize>> {
+    INIT_REFS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Serializes the asynchronous operations, which share precomputed state in liboqs
+#[cfg(feature = "std")]
+static ASYNC_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Take [`ASYNC_LOCK`]; a panic cannot leave the `()` behind it inconsistent
+#[cfg(feature = "std")]
+fn async_lock() -> std::sync::MutexGuard<'static, ()> {
+    ASYNC_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Take a reference on the global state of `kem`, initializing it if needed
+#[cfg(feature = "std")]
+fn acquire_init(algorithm: Algorithm, kem: *const ffi::OQS_KEM) -> Result<()> {
+    let mut refs = init_refs();
+    let count = refs.entry(algorithm).or_insert(0);
+    if *count == 0 {
+        status_to_result(unsafe { ffi::OQS_KEM_init(kem) })?;
+    }
+    *count += 1;
+    Ok(())
+}
+
+/// Release a reference taken by `acquire_init`,
+/// freeing the global state once no references remain
+#[cfg(feature = "std")]
+fn release_init(algorithm: Algorithm) -> Result<()> {
+    let mut refs = init_refs();
+    if let Some(count) = refs.get_mut(&algorithm) {
+        *count -= 1;
+        if *count == 0 {
+            refs.remove(&algorithm);
+        }
+    }
+    if refs.is_empty() {
+        status_to_result(unsafe { ffi::OQS_KEM_deinit() })?;
+    }
+    Ok(())
+}
+
+/// Free the global state of liboqs, see [`crate::destroy`]
+pub(crate) fn destroy() -> Result<()> {
+    #[cfg(feature = "std")]
+    {
+        let refs = init_refs();
+        if !refs.is_empty() {
+            return Ok(());
+        }
+        status_to_result(unsafe { ffi::OQS_KEM_deinit() })
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        status_to_result(unsafe { ffi::OQS_KEM_deinit() })
+    }
+}
+
+impl core::convert::TryFrom<Algorithm> for Kem {
+    type Error = crate::Error;
+    fn try_from(alg: Algorithm) -> Result<Kem> {
+        Kem::new(alg)
+    }
+}
+
+/// A public key tagged with the algorithm it belongs to
+///
+/// Construct it through `TryFrom<(Algorithm, Vec<u8>)>`, which checks the length
+/// of the key against the algorithm.
+///
+/// Optional support for `serde` if that feature is enabled.
+/// Deserialization does not validate the key length.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TaggedPublicKey {
+    algorithm: Algorithm,
+    public_key: PublicKey,
+}
+
+impl TaggedPublicKey {
+    /// Get the algorithm of this key
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Get the public key
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    /// Split into the algorithm and the public key
+    pub fn into_parts(self) -> (Algorithm, PublicKey) {
+        (self.algorithm, self.public_key)
+    }
+}
+
+/// A public key together with its secret key
+///
+/// Returned by [`Kem::keypair_struct`]. The secret key is scrubbed when the
+/// `KeyPair` is dropped.
+///
+/// Optional support for `serde` if that feature is enabled, as a single object
+/// with `public` and `secret` fields.
+/// Deserialization does not validate the key lengths.
+/// The `Debug` output leaves out the secret key.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KeyPair {
+    /// The public key
+    pub public: PublicKey,
+    /// The secret key
+    pub secret: SecretKey,
+}
+
+impl KeyPair {
+    /// Split into the public and the secret key
+    ///
+    /// The secret key is no longer scrubbed automatically afterwards.
+    pub fn into_parts(mut self) -> (PublicKey, SecretKey) {
+        let public = PublicKey {
+            bytes: core::mem::take(&mut self.public.bytes),
+        };
+        let secret = SecretKey {
+            bytes: core::mem::take(&mut self.secret.bytes),
+        };
+        (public, secret)
+    }
+}
+
+impl Drop for KeyPair {
+    fn drop(&mut self) {
+        crate::util::scrub(&mut self.secret.bytes);
+    }
+}
+
+impl core::fmt::Debug for KeyPair {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("KeyPair")
+            .field("public", &self.public)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A secret key that can decapsulate only once
+///
+/// [`OneShotSecretKey::decapsulate`] consumes the wrapper, so the type system
+/// rules out a second use of the key, which is fatal for schemes that are only
+/// secure for ephemeral keys. The key is scrubbed once it has been used, and
+/// also if the wrapper is dropped unused.
+///
+/// Unlike the reuse protection of [`KemBuilder::reuse_protection`], this needs
+/// no bookkeeping at runtime, but it also cannot stop copies of the key made
+/// before wrapping it. The `Debug` output does not show the key.
+pub struct OneShotSecretKey(SecretKey);
+
+impl OneShotSecretKey {
+    /// Take ownership of `sk` for a single decapsulation
+    pub fn new(sk: SecretKey) -> Self {
+        OneShotSecretKey(sk)
+    }
+
+    /// Decapsulate `ct` with the key and scrub the key
+    ///
+    /// The key is consumed and scrubbed whether or not decapsulation succeeds.
+    pub fn decapsulate<'a, C: Into<CiphertextRef<'a>>>(
+        self,
+        kem: &Kem,
+        ct: C,
+    ) -> Result<SharedSecret> {
+        kem.decapsulate(&self.0, ct)
+    }
+}
+
+impl From<SecretKey> for OneShotSecretKey {
+    fn from(sk: SecretKey) -> Self {
+        OneShotSecretKey::new(sk)
+    }
+}
+
+impl Drop for OneShotSecretKey {
+    fn drop(&mut self) {
+        crate::util::scrub(&mut self.0.bytes);
+    }
+}
+
+impl core::fmt::Debug for OneShotSecretKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OneShotSecretKey").finish_non_exhaustive()
+    }
+}
+
+/// The first half of a split encapsulation, see [`Kem::begin_encapsulation`]
+///
+/// Keeps the ciphertext together with the ephemeral secret it was made with, so
+/// [`SplitEncapsulation::finish`] cannot be given a mismatched pair. Finishing
+/// consumes the handle; the ephemeral secret is scrubbed when it is dropped.
+/// The `Debug` output does not show the ephemeral secret.
+pub struct SplitEncapsulation {
+    algorithm: Algorithm,
+    ct: Ciphertext,
+    es: EphemeralSecret,
+}
+
+impl core::fmt::Debug for SplitEncapsulation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SplitEncapsulation")
+            .field("algorithm", &self.algorithm)
+            .field("ct", &self.ct)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SplitEncapsulation {
+    /// The ciphertext, which can be sent before the public key is known
+    pub fn ciphertext(&self) -> &Ciphertext {
+        &self.ct
+    }
+
+    /// Compute the shared secret for `pk`
+    ///
+    /// Returns [`Error::AlgorithmMismatch`] if `kem` is not for the algorithm
+    /// that began the encapsulation. See [`Kem::encapsulate_shared_secret`] for
+    /// the other errors.
+    pub fn finish<'a, P: Into<PublicKeyRef<'a>>>(self, kem: &Kem, pk: P) -> Result<SharedSecret> {
+        if kem.algorithm() != self.algorithm {
+            return Err(Error::AlgorithmMismatch);
+        }
+        let pk: PublicKeyRef = pk.into();
+        kem.encapsulate_shared_secret(&self.ct, &self.es, pk)
+    }
+}
+
+impl Drop for SplitEncapsulation {
+    fn drop(&mut self) {
+        crate::util::scrub(&mut self.es.bytes);
+    }
+}
+
+impl core::convert::TryFrom<(Algorithm, Vec<u8>)> for TaggedPublicKey {
+    type Error = crate::Error;
+    /// Validate `bytes` as a public key for `algorithm`
+    ///
+    /// Fails with [`Error::AlgorithmDisabled`] if the algorithm is not available
+    /// and with [`Error::InvalidLength`] if the key has the wrong length.
+    fn try_from((algorithm, bytes): (Algorithm, Vec<u8>)) -> Result<TaggedPublicKey> {
+        let kem = Kem::new(algorithm)?;
+        check_length(kem.length_public_key(), &bytes)?;
+        Ok(TaggedPublicKey {
+            algorithm,
+            public_key: PublicKey { bytes },
+        })
+    }
+}
+
+impl<'a> From<&'a TaggedPublicKey> for PublicKeyRef<'a> {
+    fn from(key: &'a TaggedPublicKey) -> PublicKeyRef<'a> {
+        PublicKeyRef::from(&key.public_key)
+    }
+}
+
+impl Kem {
+    /// Construct a new algorithm
+    ///
+    /// Returns [`Error::AlgorithmDisabled`] if the algorithm is not enabled in liboqs,
+    /// or [`Error::MissingDependency`] in the case described at
+    /// [`Algorithm::requires_openssl`]. liboqs does not say why an algorithm is
+    /// disabled, so the latter is reported for a disabled algorithm that uses
+    /// OpenSSL, if its family feature is enabled but the `openssl` feature is not.
+    ///
+    /// With the `tracing` feature, this logs a warning for algorithms that are
+    /// [cryptographically broken](Algorithm::is_cryptographically_broken).
+    pub fn new(algorithm: Algorithm) -> Result<Self> {
+        let kem = unsafe { ffi::OQS_KEM_new(algorithm_to_id(algorithm)) };
+        NonNull::new(kem).map_or_else(
+            || Err(disabled_error(algorithm)),
+            |kem| {
+                #[cfg(all(feature = "tracing", not(test)))]
+                if algorithm.is_cryptographically_broken() {
+                    tracing::warn!(
+                        "{:?} is cryptographically broken and must not be used to protect real data",
+                        algorithm
+                    );
+                }
+                Ok(Self {
+                    algorithm,
+                    kem,
+                    initialized: AtomicBool::new(false),
+                    #[cfg(feature = "std")]
+                    used_secret_keys: None,
+                    #[cfg(feature = "metrics")]
+                    rejections: core::sync::atomic::AtomicU64::new(0),
+                    #[cfg(feature = "numa")]
+                    numa_node: None,
+                })
+            },
+        )
+    }
+
+    /// Construct a `Kem` from an algorithm name in a configuration file
+    ///
+    /// The name is parsed with [`Algorithm::parse_relaxed`], so `"Kyber768"` and
+    /// `"kyber-768"` both work, and a configuration can be checked when it is
+    /// loaded. Any failure is returned as [`Error::AlgorithmUnavailable`], with the
+    /// [`safe_enabled`] algorithms that could be used instead. Its `error` is
+    /// [`Error::InvalidFormat`] if no algorithm has this name, and the error of
+    /// [`Kem::new`] if it is not enabled in the linked liboqs. With the `tracing`
+    /// feature, the error is also logged.
+    pub fn from_config_str(s: &str) -> Result<Self> {
+        let algorithm = Algorithm::parse_relaxed(s);
+        algorithm
+            .map_or(Err(Error::InvalidFormat), Kem::new)
+            .map_err(|error| {
+                let error = Error::AlgorithmUnavailable {
+                    error: alloc::boxed::Box::new(error),
+                    alternatives: safe_enabled(),
+                };
+                #[cfg(feature = "tracing")]
+                tracing::warn!("KEM algorithm {:?}: {}", s, error);
+                error
+            })
+    }
+
+    /// Deserialize an [`Algorithm`] and construct a `Kem` for it
+    ///
+    /// Meant for `#[serde(deserialize_with = "oqs::kem::Kem::deserialize_and_new")]`
+    /// on a `Kem` field. A disabled algorithm is reported as a deserialization error
+    /// that names the algorithm.
+    #[cfg(feature = "serde")]
+    pub fn deserialize_and_new<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> core::result::Result<Self, D::Error> {
+        use serde::de::Error as _;
+        let algorithm = Algorithm::deserialize(deserializer)?;
+        Kem::new(algorithm).map_err(|e| {
+            D::Error::custom(format_args!(
+                "cannot use KEM algorithm {}: {}",
+                algorithm.name(),
+                e
+            ))
+        })
+    }
+
+    /// Construct a new algorithm whose output buffers are placed on a NUMA node
+    ///
+    /// Keys, ciphertexts and shared secrets created by this `Kem` are placed on
+    /// `node` through libnuma, so that a worker thread running on that node reads
+    /// them from local memory. This mostly matters for the large keys of
+    /// Classic McEliece; buffers smaller than two pages are not moved, and the
+    /// internal state of liboqs is not affected.
+    ///
+    /// Only supported on Linux. Elsewhere, or if libnuma reports that NUMA is not
+    /// available, this behaves like [`Kem::new`]. Returns [`Error::OperationNotSupported`]
+    /// if NUMA is available but `node` does not exist.
+    #[cfg(feature = "numa")]
+    pub fn new_on_node(algorithm: Algorithm, node: u32) -> Result<Self> {
+        let mut kem = Self::new(algorithm)?;
+        if crate::numa::has_node(node) {
+            kem.numa_node = Some(node);
+        } else if crate::numa::has_node(0) {
+            return Err(Error::OperationNotSupported);
+        }
+        Ok(kem)
+    }
+
+    /// Start configuring a `Kem` for `algorithm`, see [`KemBuilder`]
+    pub fn builder(algorithm: Algorithm) -> KemBuilder {
+        KemBuilder::new(algorithm)
+    }
+
+    /// Get the algorithm used by this `Kem`
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Get the version of the implementation
+    pub fn version(&self) -> &'static str {
+        let kem = unsafe { self.kem.as_ref() };
+        // SAFETY: The alg_version from ffi must be a proper null terminated C string
+        let cstr = unsafe { CStr::from_ptr(kem.alg_version) };
+        cstr.to_str()
+            .expect("Algorithm version strings must be UTF-8")
+    }
+
+    /// Get the claimed nist level
+    pub fn claimed_nist_level(&self) -> u8 {
+        let kem = unsafe { self.kem.as_ref() };
+        kem.claimed_nist_level
+    }
+
+    /// Is the algorithm ind_cca secure
+    pub fn is_ind_cca(&self) -> bool {
+        let kem = unsafe { self.kem.as_ref() };
+        kem.ind_cca
+    }
+
+    /// Does the algorithm only use AES and SHA-2 as symmetric primitives
+    ///
+    /// True for the Kyber `90s` variants, which replace the SHA-3 and SHAKE
+    /// functions of Kyber by AES-256-CTR, SHA-256 and SHA-512.
+    ///
+    /// This only describes the design of the algorithm. It does not mean that
+    /// liboqs uses a FIPS-validated implementation of these primitives, nor that
+    /// the algorithm itself is FIPS-approved; liboqs exposes no flag for a
+    /// FIPS-validated build, so this crate cannot report one.
+    pub fn uses_fips_primitives(&self) -> bool {
+        matches!(
+            self.algorithm,
+            Algorithm::Kyber512_90s | Algorithm::Kyber768_90s | Algorithm::Kyber1024_90s
+        )
+    }
+
+    /// Security notion of the split encapsulation flow
+    ///
+    /// [`Kem::encapsulate_ciphertext`] creates the ciphertext before the public
+    /// key is known, so the re-encryption check that makes the one-shot
+    /// [`Kem::encapsulate`] IND-CCA cannot bind the ciphertext to the key.
+    /// The two-phase flow is therefore only IND-CPA, regardless of
+    /// [`Kem::is_ind_cca`], and is meant for ephemeral key exchange.
+    ///
+    /// Returns [`SecurityNotion::Unsupported`] if this algorithm has no
+    /// split encapsulation.
+    pub fn split_encapsulation_security(&self) -> SecurityNotion {
+        match self.length_ephemeral_secret() {
+            None => SecurityNotion::Unsupported,
+            Some(_) => SecurityNotion::IndCpa,
+        }
+    }
+
+    /// Returns which operations liboqs provides for this algorithm
+    ///
+    /// This reads the optional function pointers of the underlying `OQS_KEM`,
+    /// which is useful to find out what a partially implemented algorithm of a
+    /// liboqs fork supports. [`Kem::keypair_async`], [`Kem::init`] and [`Kem::deinit`]
+    /// are exported by liboqs as plain functions rather than function pointers,
+    /// so they cannot be inspected this way.
+    pub fn available_operations(&self) -> OperationFlags {
+        let kem = unsafe { self.kem.as_ref() };
+        let mut flags = OperationFlags::empty();
+        if kem.keypair.is_some() {
+            flags |= OperationFlags::KEYPAIR;
+        }
+        if kem.encaps.is_some() {
+            flags |= OperationFlags::ENCAPSULATE;
+        }
+        if kem.decaps.is_some() {
+            flags |= OperationFlags::DECAPSULATE;
+        }
+        if kem.async_encaps.is_some() {
+            flags |= OperationFlags::ASYNC_ENCAPSULATE;
+        }
+        if self.length_ephemeral_secret().is_some() {
+            flags |= OperationFlags::SPLIT_ENCAPSULATION;
+        }
+        flags
     }
 
     /// Get the length of the public key
     pub fn length_public_key(&self) -> usize {
         let kem = unsafe { self.kem.as_ref() };
-        kem.length_public_key
+        kem.length_public_key
+    }
+
+    /// Get the length of the secret key
+    pub fn length_secret_key(&self) -> usize {
+        let kem = unsafe { self.kem.as_ref() };
+        kem.length_secret_key
+    }
+
+    /// Get the length of the ciphertext
+    pub fn length_ciphertext(&self) -> usize {
+        let kem = unsafe { self.kem.as_ref() };
+        kem.length_ciphertext
+    }
+
+    /// Get the length of a shared secret
+    pub fn length_shared_secret(&self) -> usize {
+        let kem = unsafe { self.kem.as_ref() };
+        kem.length_shared_secret
+    }
+
+    /// Get the lengths of all objects at once
+    ///
+    /// To get them without constructing a `Kem`, use [`Algorithm::sizes`].
+    pub fn sizes(&self) -> KemSizes {
+        KemSizes {
+            public_key: self.length_public_key(),
+            secret_key: self.length_secret_key(),
+            ciphertext: self.length_ciphertext(),
+            shared_secret: self.length_shared_secret(),
+        }
+    }
+
+    /// Get the length of an ephemeral secret
+    ///
+    /// Ephemeral secrets are only used by the split encapsulation,
+    /// [`Kem::encapsulate_ciphertext`] and [`Kem::encapsulate_shared_secret`].
+    /// Only the SIKE and SIDH variants of this fork provide it; other algorithms
+    /// return `None`.
+    pub fn length_ephemeral_secret(&self) -> Option<usize> {
+        let kem = unsafe { self.kem.as_ref() };
+        if kem.encaps_shared_secret.is_none() || kem.length_ephemeral_secret == 0 {
+            None
+        } else {
+            Some(kem.length_ephemeral_secret)
+        }
+    }
+
+    /// Obtain a secret key objects from bytes
+    ///
+    /// Returns None if the secret key is not the correct length.
+    pub fn secret_key_from_bytes<'a>(&self, buf: &'a [u8]) -> Option<SecretKeyRef<'a>> {
+        if self.length_secret_key() != buf.len() {
+            None
+        } else {
+            Some(SecretKeyRef::new(buf))
+        }
+    }
+
+    /// Obtain a public key from bytes
+    ///
+    /// Returns None if the public key is not the correct length.
+    pub fn public_key_from_bytes<'a>(&self, buf: &'a [u8]) -> Option<PublicKeyRef<'a>> {
+        if self.length_public_key() != buf.len() {
+            None
+        } else {
+            Some(PublicKeyRef::new(buf))
+        }
+    }
+
+    /// Obtain a public key from bytes and check them against a CRC-32
+    ///
+    /// Returns [`Error::ChecksumMismatch`] if `crc` does not match `buf`,
+    /// for example because the key was corrupted in transit.
+    pub fn public_key_from_bytes_checked<'a>(
+        &self,
+        buf: &'a [u8],
+        crc: u32,
+    ) -> Result<PublicKeyRef<'a>> {
+        let pk = self.try_public_key_ref(buf)?;
+        if crate::util::crc32(buf) != crc {
+            return Err(Error::ChecksumMismatch);
+        }
+        Ok(pk)
+    }
+
+    /// Obtain a public key from the output of [`PublicKey::with_crc`]
+    ///
+    /// The last four bytes of `buf` must be the big-endian CRC-32 of the key.
+    pub fn public_key_from_bytes_with_crc<'a>(&self, buf: &'a [u8]) -> Result<PublicKeyRef<'a>> {
+        if buf.len() != self.length_public_key() + 4 {
+            return Err(Error::InvalidLength {
+                expected: self.length_public_key() + 4,
+                got: buf.len(),
+            });
+        }
+        let (key, crc) = buf.split_at(self.length_public_key());
+        let crc = u32::from_be_bytes([crc[0], crc[1], crc[2], crc[3]]);
+        self.public_key_from_bytes_checked(key, crc)
+    }
+
+    /// Obtain a ciphertext from bytes
+    ///
+    /// Returns None if the ciphertext is not the correct length.
+    pub fn ciphertext_from_bytes<'a>(&self, buf: &'a [u8]) -> Option<CiphertextRef<'a>> {
+        if self.length_ciphertext() != buf.len() {
+            None
+        } else {
+            Some(CiphertextRef::new(buf))
+        }
+    }
+
+    /// Compare the length of `bytes` to the ciphertext length, e.g. for logging
+    ///
+    /// A ciphertext that is a few bytes off usually points at a framing bug in the
+    /// transport rather than at the KEM. [`Kem::decapsulate`] reports the same
+    /// mismatch as [`Error::InvalidCiphertextLength`].
+    pub fn diagnose_ciphertext(&self, bytes: &[u8]) -> CiphertextDiagnosis {
+        let expected = self.length_ciphertext();
+        match bytes.len() {
+            len if len < expected => CiphertextDiagnosis::TooShort(expected - len),
+            len if len > expected => CiphertextDiagnosis::TooLong(len - expected),
+            _ => CiphertextDiagnosis::Exact,
+        }
+    }
+
+    /// Obtain a secret key from bytes
+    ///
+    /// Returns None if the shared secret is not the correct length.
+    pub fn shared_secret_from_bytes<'a>(&self, buf: &'a [u8]) -> Option<SharedSecretRef<'a>> {
+        if self.length_shared_secret() != buf.len() {
+            None
+        } else {
+            Some(SharedSecretRef::new(buf))
+        }
+    }
+
+    /// Obtain an ephemeral secret from bytes
+    ///
+    /// Returns None if the ephemeral secret is not the correct length,
+    /// or if the algorithm has no ephemeral secrets.
+    pub fn ephemeral_secret_from_bytes<'a>(&self, buf: &'a [u8]) -> Option<EphemeralSecretRef<'a>> {
+        if self.length_ephemeral_secret() != Some(buf.len()) {
+            None
+        } else {
+            Some(EphemeralSecretRef::new(buf))
+        }
+    }
+
+    /// Obtain a secret key object from bytes
+    ///
+    /// Returns [`Error::InvalidLength`] if the secret key is not the correct length.
+    pub fn try_secret_key_ref<'a>(&self, buf: &'a [u8]) -> Result<SecretKeyRef<'a>> {
+        check_length(self.length_secret_key(), buf)?;
+        Ok(SecretKeyRef::new(buf))
+    }
+
+    /// Obtain a public key object from bytes
+    ///
+    /// Returns [`Error::InvalidLength`] if the public key is not the correct length.
+    pub fn try_public_key_ref<'a>(&self, buf: &'a [u8]) -> Result<PublicKeyRef<'a>> {
+        check_length(self.length_public_key(), buf)?;
+        Ok(PublicKeyRef::new(buf))
+    }
+
+    /// Obtain a public key from a buffer that may be padded with trailing zeros
+    ///
+    /// A compatibility shim for keys stored in fixed-width fields, such as database
+    /// columns that pad their contents: if `buf` is longer than a public key and all
+    /// excess bytes are zero, the prefix is the key. Prefer the strict
+    /// [`Kem::try_public_key_ref`] wherever the length is known, as this cannot
+    /// tell padding from trailing zeros that were appended by mistake.
+    ///
+    /// Returns [`Error::InvalidLength`] if `buf` is shorter than a public key and
+    /// [`Error::InvalidFormat`] if any excess byte is not zero.
+    pub fn public_key_from_padded<'a>(&self, buf: &'a [u8]) -> Result<PublicKeyRef<'a>> {
+        let len = self.length_public_key();
+        if buf.len() < len {
+            return Err(Error::InvalidLength {
+                expected: len,
+                got: buf.len(),
+            });
+        }
+        let (pk, padding) = buf.split_at(len);
+        if padding.iter().any(|&byte| byte != 0) {
+            return Err(Error::InvalidFormat);
+        }
+        Ok(PublicKeyRef::new(pk))
+    }
+
+    /// Take a public key from the front of `buf`
+    ///
+    /// Returns the public key and the bytes following it, for parsing keys
+    /// embedded in a larger message.
+    /// Returns [`Error::InvalidLength`] if `buf` is shorter than a public key.
+    pub fn public_key_take<'a>(&self, buf: &'a [u8]) -> Result<(PublicKeyRef<'a>, &'a [u8])> {
+        let len = self.length_public_key();
+        if buf.len() < len {
+            return Err(Error::InvalidLength {
+                expected: len,
+                got: buf.len(),
+            });
+        }
+        let (pk, rest) = buf.split_at(len);
+        Ok((PublicKeyRef::new(pk), rest))
+    }
+
+    /// Obtain a ciphertext object from bytes
+    ///
+    /// Returns [`Error::InvalidLength`] if the ciphertext is not the correct length.
+    pub fn try_ciphertext_ref<'a>(&self, buf: &'a [u8]) -> Result<CiphertextRef<'a>> {
+        check_length(self.length_ciphertext(), buf)?;
+        Ok(CiphertextRef::new(buf))
+    }
+
+    /// Obtain a shared secret object from bytes
+    ///
+    /// Returns [`Error::InvalidLength`] if the shared secret is not the correct length.
+    pub fn try_shared_secret_ref<'a>(&self, buf: &'a [u8]) -> Result<SharedSecretRef<'a>> {
+        check_length(self.length_shared_secret(), buf)?;
+        Ok(SharedSecretRef::new(buf))
+    }
+
+    /// Obtain an ephemeral secret object from bytes
+    ///
+    /// Returns [`Error::InvalidLength`] if the ephemeral secret is not the correct length,
+    /// and [`Error::OperationNotSupported`] if the algorithm has no ephemeral secrets.
+    pub fn try_ephemeral_secret_ref<'a>(&self, buf: &'a [u8]) -> Result<EphemeralSecretRef<'a>> {
+        let expected = self
+            .length_ephemeral_secret()
+            .ok_or(Error::OperationNotSupported)?;
+        check_length(expected, buf)?;
+        Ok(EphemeralSecretRef::new(buf))
+    }
+
+    /// Initialize the KEM
+    ///
+    /// Builds the global state some algorithms (SIKE) need, such as precomputation tables.
+    /// For these algorithms, see [`Algorithm::requires_init`], encapsulation and
+    /// decapsulation return [`Error::NotInitialized`] until `init()` is called.
+    /// With the `std` feature, this state is reference counted: it is built by the first
+    /// `Kem` of an algorithm to call `init()` and freed again once every initialized
+    /// `Kem` has called [`Kem::deinit`] or has been dropped. Calling `init()` more than
+    /// once on the same `Kem` has no further effect.
+    ///
+    /// Without the `std` feature, this is not thread-safe, and the state is only freed
+    /// through [`Kem::deinit`].
+    pub fn init(&self) -> Result<()> {
+        if self.initialized.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        #[cfg(feature = "std")]
+        let result = acquire_init(self.algorithm, self.kem.as_ptr());
+        #[cfg(not(feature = "std"))]
+        let result = status_to_result(unsafe { ffi::OQS_KEM_init(self.kem.as_ptr()) });
+        if result.is_err() {
+            self.initialized.store(false, Ordering::SeqCst);
+        }
+        result
+    }
+
+    /// Uninitialize the KEM
+    ///
+    /// Releases what [`Kem::init`] acquired; does nothing if this `Kem` is not initialized.
+    /// Dropping an initialized `Kem` has the same effect.
+    ///
+    /// Note that [`crate::kem_deinit`] frees the global state regardless of any
+    /// other initialized `Kem`s.
+    pub fn deinit(&self) -> Result<()> {
+        if !self.initialized.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        #[cfg(feature = "std")]
+        {
+            release_init(self.algorithm)
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            status_to_result(unsafe { ffi::OQS_KEM_deinit() })
+        }
+    }
+
+    /// Time one `init()`/`deinit()` cycle of the global state of this algorithm
+    ///
+    /// Measures what an initialization costs, to decide between initializing
+    /// every `Kem` and sharing initialized ones, e.g. through a pool. Returns
+    /// `None` for algorithms that do not [require initialization](Algorithm::requires_init).
+    ///
+    /// This builds and then frees the global state liboqs keeps for SIKE, and
+    /// with it that of every other algorithm. To not pull that state away from
+    /// other users, this returns [`Error::OperationNotSupported`] while any `Kem`,
+    /// including this one, is initialized. Requires the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn time_init(&self) -> Result<Option<core::time::Duration>> {
+        if !self.algorithm.requires_init() {
+            return Ok(None);
+        }
+        let refs = init_refs();
+        if !refs.is_empty() {
+            return Err(Error::OperationNotSupported);
+        }
+        let start = std::time::Instant::now();
+        let result = status_to_result(unsafe { ffi::OQS_KEM_init(self.kem.as_ptr()) });
+        let elapsed = start.elapsed();
+        status_to_result(unsafe { ffi::OQS_KEM_deinit() })?;
+        drop(refs);
+        result.map(|()| Some(elapsed))
+    }
+
+    /// Recover the public key from a secret key
+    ///
+    /// Supported where the secret key embeds a copy of the public key:
+    /// Kyber, Saber, HQC, FrodoKEM (except the 1CCA variants) and the
+    /// uncompressed SIKE variants. Other algorithms return
+    /// [`Error::OperationNotSupported`], as their public key is stored separately.
+    pub fn public_from_secret<'a, S: Into<SecretKeyRef<'a>>>(&self, sk: S) -> Result<PublicKey> {
+        let sk = sk.into();
+        self.check_secret_key_length(&sk)?;
+        let offset = self.embedded_public_key_offset()?;
+        Ok(PublicKey {
+            bytes: sk.bytes[offset..offset + self.length_public_key()].to_vec(),
+        })
+    }
+
+    /// Where the secret key embeds the public key, see [`Kem::public_from_secret`]
+    fn embedded_public_key_offset(&self) -> Result<usize> {
+        let (pk_len, sk_len) = (self.length_public_key(), self.length_secret_key());
+        let offset = match self.algorithm.family() {
+            // sk = cpa_sk || pk || H(pk) || z
+            Family::Kyber | Family::Saber => sk_len - pk_len - 64,
+            // sk = s || pk || S^T || H(pk), where s is as long as the shared secret
+            Family::FrodoKem if !self.algorithm.is_1cca() => self.length_shared_secret(),
+            // sk = seed || pk and sk = s || sk3 || pk
+            Family::Hqc => sk_len - pk_len,
+            Family::Sike if !self.algorithm.is_compressed() => sk_len - pk_len,
+            _ => return Err(Error::OperationNotSupported),
+        };
+        Ok(offset)
+    }
+
+    /// Length of a [`CompactSecretKey`], see [`SecretKey::compact`]
+    ///
+    /// Returns [`Error::OperationNotSupported`] if the secret key does not embed the public key.
+    pub fn length_compact_secret_key(&self) -> Result<usize> {
+        self.embedded_public_key_offset()?;
+        Ok(self.length_secret_key() - self.length_public_key())
+    }
+
+    /// Obtain a compact secret key object from bytes
+    ///
+    /// Returns [`Error::InvalidLength`] if the compact secret key is not the correct length,
+    /// and [`Error::OperationNotSupported`] if the algorithm has no compact secret keys.
+    pub fn try_compact_secret_key_ref<'a>(&self, buf: &'a [u8]) -> Result<CompactSecretKeyRef<'a>> {
+        check_length(self.length_compact_secret_key()?, buf)?;
+        Ok(CompactSecretKeyRef::new(buf))
+    }
+
+    /// Rebuild a secret key from its compact form and its public key
+    ///
+    /// Reinserts `pk` where [`SecretKey::compact`] removed it. The result is only
+    /// the original secret key if `pk` is the matching public key; with another
+    /// key of the right length, decapsulation produces wrong shared secrets.
+    /// Returns [`Error::OperationNotSupported`] if the algorithm has no compact secret keys.
+    pub fn secret_key_from_compact<
+        'a,
+        'b,
+        C: Into<CompactSecretKeyRef<'a>>,
+        P: Into<PublicKeyRef<'b>>,
+    >(
+        &self,
+        compact: C,
+        pk: P,
+    ) -> Result<SecretKey> {
+        let (compact, pk) = (compact.into(), pk.into());
+        let offset = self.embedded_public_key_offset()?;
+        check_length(self.length_compact_secret_key()?, compact.bytes)?;
+        check_length(self.length_public_key(), pk.bytes)?;
+        let mut bytes = alloc::vec![0; self.length_secret_key()];
+        bytes[..offset].copy_from_slice(&compact.bytes[..offset]);
+        bytes[offset..offset + pk.bytes.len()].copy_from_slice(pk.bytes);
+        bytes[offset + pk.bytes.len()..].copy_from_slice(&compact.bytes[offset..]);
+        Ok(SecretKey { bytes })
+    }
+
+    /// Generate a new keypair
+    pub fn keypair(&self) -> Result<(PublicKey, SecretKey)> {
+        let kem = unsafe { self.kem.as_ref() };
+        let func = kem.keypair.unwrap();
+        let mut pk = PublicKey {
+            bytes: self.output_buffer(kem.length_public_key),
+        };
+        let mut sk = SecretKey {
+            bytes: self.output_buffer(kem.length_secret_key),
+        };
+        let status = unsafe { func(pk.bytes.as_mut_ptr(), sk.bytes.as_mut_ptr()) };
+        status_to_result(status)?;
+        Ok((pk, sk))
+    }
+
+    /// Generate a new keypair, writing the secret key to `sk_out`
+    ///
+    /// Only the public key is returned; the secret key goes straight into the
+    /// caller's buffer, e.g. memory registered with secure hardware, and never
+    /// passes through a `Vec` of this crate. Use it with [`Kem::try_secret_key_ref`].
+    /// If key generation fails, `sk_out` is scrubbed.
+    ///
+    /// Returns [`Error::InvalidSecretKeyLength`] if `sk_out` is not
+    /// [`Kem::length_secret_key`] bytes long.
+    pub fn keypair_sk_into(&self, sk_out: &mut [u8]) -> Result<PublicKey> {
+        let kem = unsafe { self.kem.as_ref() };
+        if sk_out.len() != kem.length_secret_key {
+            return Err(Error::InvalidSecretKeyLength {
+                expected: kem.length_secret_key,
+                got: sk_out.len(),
+            });
+        }
+        let func = kem.keypair.unwrap();
+        let mut pk = PublicKey {
+            bytes: self.output_buffer(kem.length_public_key),
+        };
+        let status = unsafe { func(pk.bytes.as_mut_ptr(), sk_out.as_mut_ptr()) };
+        if let Err(error) = status_to_result(status) {
+            crate::util::scrub(sk_out);
+            return Err(error);
+        }
+        Ok(pk)
+    }
+
+    /// Generate a new keypair for key rotation, keeping the initialized state
+    ///
+    /// The precomputed tables of SIKE and SIDH do not depend on the keys, so
+    /// rotating keys needs no [`Kem::deinit`] and [`Kem::init`] cycle: this
+    /// initializes the `Kem` if it is not yet and then only generates a keypair.
+    /// For algorithms without such state, see [`Algorithm::requires_init`],
+    /// it is the same as [`Kem::keypair`].
+    pub fn rekey(&self) -> Result<(PublicKey, SecretKey)> {
+        if self.algorithm.requires_init() {
+            self.init()?;
+        }
+        self.keypair()
+    }
+
+    /// Generate a new keypair and check that its halves correspond
+    ///
+    /// Performs a pairwise consistency test, as some compliance regimes require:
+    /// encapsulates to the new public key, decapsulates with the new secret key
+    /// and compares the shared secrets in constant time. If they differ, the
+    /// secret key is scrubbed and [`Error::PairwiseConsistencyFailed`] is returned.
+    ///
+    /// This costs one encapsulation and decapsulation on top of the key generation.
+    /// The test does not count as a use of the secret key for reuse protection.
+    pub fn keypair_tested(&self) -> Result<(PublicKey, SecretKey)> {
+        let (pk, mut sk) = self.keypair()?;
+        if !self.pairwise_consistent(PublicKeyRef::from(&pk), SecretKeyRef::from(&sk))? {
+            crate::util::scrub(&mut sk.bytes);
+            return Err(Error::PairwiseConsistencyFailed);
+        }
+        Ok((pk, sk))
+    }
+
+    /// Check that a key pair loaded from storage is consistent
+    ///
+    /// Runs the pairwise consistency test of [`Kem::keypair_tested`] on an existing
+    /// key pair: encapsulates to `pk`, decapsulates with `sk` and compares the
+    /// shared secrets in constant time. A corrupted secret key otherwise goes
+    /// unnoticed, as decapsulation with it implicitly rejects every ciphertext.
+    /// Returns `Ok(false)` if the shared secrets differ, and errors for keys of the
+    /// wrong length or a failing operation.
+    ///
+    /// This costs one encapsulation and decapsulation. The test does not count as
+    /// a use of the secret key for reuse protection.
+    pub fn verify_keypair<'a, 'b, P: Into<PublicKeyRef<'a>>, S: Into<SecretKeyRef<'b>>>(
+        &self,
+        pk: P,
+        sk: S,
+    ) -> Result<bool> {
+        let sk = sk.into();
+        self.check_secret_key_length(&sk)?;
+        self.pairwise_consistent(pk.into(), sk)
+    }
+
+    /// Encapsulate to `pk`, decapsulate with `sk` and compare the shared secrets
+    fn pairwise_consistent(&self, pk: PublicKeyRef, sk: SecretKeyRef) -> Result<bool> {
+        let (ct, mut encaps_ss) = self.encapsulate(pk)?;
+        let encaps_ss = crate::util::ScrubGuard(&mut encaps_ss.bytes);
+        let mut decaps_ss = alloc::vec![0; self.length_shared_secret()];
+        let decaps_ss = crate::util::ScrubGuard(&mut decaps_ss);
+        let kem = unsafe { self.kem.as_ref() };
+        let func = kem.decaps.unwrap();
+        let status = unsafe {
+            func(
+                decaps_ss.0.as_mut_ptr(),
+                ct.bytes.as_ptr(),
+                sk.bytes.as_ptr(),
+            )
+        };
+        status_to_result(status)?;
+        Ok(crate::util::ct_eq(encaps_ss.0, decaps_ss.0))
+    }
+
+    /// Generate a new keypair on a helper thread, giving up once `cancel` is set
+    ///
+    /// The key generation itself is a single call into liboqs and cannot be
+    /// interrupted. Instead, this waits for the helper thread and checks `cancel`
+    /// every few milliseconds; once it is set, [`Error::Cancelled`] is returned
+    /// right away. If `cancel` is already set, no helper thread is started.
+    ///
+    /// A cancelled helper thread is detached: it keeps running, and so keeps its
+    /// CPU time and memory, until the key generation finishes, after which the
+    /// result is scrubbed and discarded. Cancelling many key generations of a
+    /// slow algorithm in a row can therefore pile up threads.
+    ///
+    /// The helper thread cannot borrow `self`, so it generates the key pair with
+    /// its own `Kem` for the same algorithm. That `Kem` takes over the options
+    /// that affect key generation: it is initialized if `self` is, and with the
+    /// `numa` feature, places the keys on the NUMA node of `self`. The generator
+    /// set with [`KemBuilder::rng`] is global and so is used as well. Reuse
+    /// protection only concerns decapsulation and does not apply here.
+    #[cfg(feature = "std")]
+    pub fn keypair_cancellable(&self, cancel: &AtomicBool) -> Result<(PublicKey, SecretKey)> {
+        use std::sync::mpsc::{self, RecvTimeoutError};
+        use std::time::Duration;
+
+        if cancel.load(Ordering::Relaxed) {
+            return Err(Error::Cancelled);
+        }
+        let algorithm = self.algorithm;
+        let initialized = self.initialized.load(Ordering::SeqCst);
+        #[cfg(feature = "numa")]
+        let numa_node = self.numa_node;
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            #[cfg_attr(not(feature = "numa"), allow(unused_mut))]
+            let result = Kem::new(algorithm).and_then(|mut kem| {
+                #[cfg(feature = "numa")]
+                {
+                    kem.numa_node = numa_node;
+                }
+                if initialized {
+                    kem.init()?;
+                }
+                kem.keypair()
+            });
+            if let Err(mpsc::SendError(Ok((_, mut sk)))) = sender.send(result) {
+                crate::util::scrub(&mut sk.bytes);
+            }
+        });
+        loop {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(Error::Cancelled);
+            }
+            match receiver.recv_timeout(Duration::from_millis(10)) {
+                Ok(result) => return result,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return Err(Error::Error),
+            }
+        }
+    }
+
+    /// Generate a new keypair as a [`KeyPair`]
+    pub fn keypair_struct(&self) -> Result<KeyPair> {
+        let (public, secret) = self.keypair()?;
+        Ok(KeyPair { public, secret })
+    }
+
+    /// Generate a new keypair
+    pub fn keypair_async(&self) -> Result<(PublicKey, SecretKey)> {
+        let kem = unsafe { self.kem.as_ref() };
+        let mut pk = PublicKey {
+            bytes: self.output_buffer(kem.length_public_key),
+        };
+        let mut sk = SecretKey {
+            bytes: self.output_buffer(kem.length_secret_key),
+        };
+        #[cfg(feature = "std")]
+        let _lock = async_lock();
+        let status = unsafe { ffi::OQS_KEM_async_keypair(self.kem.as_ptr(), pk.bytes.as_mut_ptr(), sk.bytes.as_mut_ptr()) };
+        status_to_result(status)?;
+        Ok((pk, sk))
+    }
+
+    /// Encapsulate to the provided public key
+    pub fn encapsulate<'a, P: Into<PublicKeyRef<'a>>>(
+        &self,
+        pk: P,
+    ) -> Result<(Ciphertext, SharedSecret)> {
+        let pk = pk.into();
+        self.check_initialized()?;
+        if pk.bytes.len() != self.length_public_key() {
+            return Err(Error::InvalidLength {
+                expected: self.length_public_key(),
+                got: pk.bytes.len(),
+            });
+        }
+        let kem = unsafe { self.kem.as_ref() };
+        let func = kem.encaps.unwrap();
+        let mut ct = Ciphertext {
+            bytes: self.output_buffer(kem.length_ciphertext),
+        };
+        let mut ss = SharedSecret {
+            bytes: self.output_buffer(kem.length_shared_secret),
+        };
+        // call encapsulate
+        let status = unsafe {
+            func(
+                ct.bytes.as_mut_ptr(),
+                ss.bytes.as_mut_ptr(),
+                pk.bytes.as_ptr(),
+            )
+        };
+        status_to_result(status)?;
+        Ok((ct, ss))
+    }
+
+    /// Does `tagged` belong to this `Kem`
+    ///
+    /// True if the key is tagged with this `Kem`'s algorithm and has the length of
+    /// its public keys. Algorithms can share key lengths, so the length check alone
+    /// does not catch a key of another algorithm.
+    pub fn accepts(&self, tagged: &TaggedPublicKey) -> bool {
+        tagged.algorithm == self.algorithm
+            && tagged.public_key.bytes.len() == self.length_public_key()
+    }
+
+    /// Encapsulate to a tagged public key
+    ///
+    /// Returns [`Error::AlgorithmMismatch`] unless [`Kem::accepts`] the key.
+    pub fn encapsulate_tagged(
+        &self,
+        tagged: &TaggedPublicKey,
+    ) -> Result<(Ciphertext, SharedSecret)> {
+        if !self.accepts(tagged) {
+            return Err(Error::AlgorithmMismatch);
+        }
+        self.encapsulate(tagged)
+    }
+
+    /// Decapsulate with the secret key belonging to a tagged public key
+    ///
+    /// Returns [`Error::AlgorithmMismatch`] unless [`Kem::accepts`] `tagged`, the
+    /// public key of `sk`. [`Kem::decapsulate`] cannot check this, as untagged
+    /// secret keys and ciphertexts do not record their algorithm.
+    pub fn decapsulate_tagged<'a, 'b, S: Into<SecretKeyRef<'a>>, C: Into<CiphertextRef<'b>>>(
+        &self,
+        tagged: &TaggedPublicKey,
+        sk: S,
+        ct: C,
+    ) -> Result<SharedSecret> {
+        if !self.accepts(tagged) {
+            return Err(Error::AlgorithmMismatch);
+        }
+        self.decapsulate(sk, ct)
+    }
+
+    /// Decapsulate with the secret key of a tagged public key into a caller-provided buffer
+    ///
+    /// Like [`Kem::decapsulate_tagged`], but writes the shared secret into `ss`
+    /// as [`Kem::decapsulate_into`] does.
+    pub fn decapsulate_tagged_into<
+        'a,
+        'b,
+        S: Into<SecretKeyRef<'a>>,
+        C: Into<CiphertextRef<'b>>,
+    >(
+        &self,
+        tagged: &TaggedPublicKey,
+        sk: S,
+        ct: C,
+        ss: &mut [u8],
+    ) -> Result<()> {
+        if !self.accepts(tagged) {
+            return Err(Error::AlgorithmMismatch);
+        }
+        self.decapsulate_into(sk, ct, ss)
+    }
+
+    /// Encapsulate to the provided public key, writing into caller-provided buffers
+    ///
+    /// `ct` must be [`Kem::length_ciphertext`] and `ss` [`Kem::length_shared_secret`]
+    /// bytes long. This does not allocate.
+    pub fn encapsulate_into<'a, P: Into<PublicKeyRef<'a>>>(
+        &self,
+        pk: P,
+        ct: &mut [u8],
+        ss: &mut [u8],
+    ) -> Result<()> {
+        let pk = pk.into();
+        self.check_initialized()?;
+        if pk.bytes.len() != self.length_public_key() {
+            return Err(Error::InvalidLength {
+                expected: self.length_public_key(),
+                got: pk.bytes.len(),
+            });
+        }
+        if ct.len() != self.length_ciphertext() {
+            return Err(Error::InvalidCiphertextLength {
+                expected: self.length_ciphertext(),
+                got: ct.len(),
+            });
+        }
+        check_length(self.length_shared_secret(), ss)?;
+        let kem = unsafe { self.kem.as_ref() };
+        let func = kem.encaps.unwrap();
+        let status = unsafe { func(ct.as_mut_ptr(), ss.as_mut_ptr(), pk.bytes.as_ptr()) };
+        status_to_result(status)
+    }
+
+    /// Encapsulate to the provided public key and pass the results to `f`
+    ///
+    /// The ciphertext and shared secret are only borrowed by `f` and are
+    /// scrubbed before this method returns, also if `f` panics.
+    ///
+    /// If the ciphertext and shared secret together fit in
+    /// [`ENCAPSULATE_WITH_STACK_SIZE`] bytes, they are placed on the stack.
+    /// Larger outputs are written to a thread-local scratch buffer, which grows
+    /// to the largest size requested on that thread and is kept for the lifetime
+    /// of the thread. Without the `std` feature, or when `f` itself calls
+    /// `encapsulate_with` for a large algorithm, a temporary heap buffer is used.
+    pub fn encapsulate_with<'a, P: Into<PublicKeyRef<'a>>, R>(
+        &self,
+        pk: P,
+        f: impl FnOnce(CiphertextRef, SharedSecretRef) -> R,
+    ) -> Result<R> {
+        let pk = pk.into();
+        self.check_initialized()?;
+        if pk.bytes.len() != self.length_public_key() {
+            return Err(Error::InvalidLength {
+                expected: self.length_public_key(),
+                got: pk.bytes.len(),
+            });
+        }
+        let needed = self.length_ciphertext() + self.length_shared_secret();
+        if needed <= ENCAPSULATE_WITH_STACK_SIZE {
+            let mut buf = [0u8; ENCAPSULATE_WITH_STACK_SIZE];
+            return self.encapsulate_into_scratch(pk, &mut buf[..needed], f);
+        }
+
+        #[cfg(feature = "std")]
+        {
+            use core::cell::RefCell;
+            std::thread_local! {
+                static SCRATCH: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+            }
+            SCRATCH.with(|scratch| match scratch.try_borrow_mut() {
+                Ok(mut scratch) => {
+                    if scratch.len() < needed {
+                        // The scratch is always scrubbed after use,
+                        // so no secrets are left behind by reallocating.
+                        scratch.resize(needed, 0);
+                    }
+                    self.encapsulate_into_scratch(pk, &mut scratch[..needed], f)
+                }
+                Err(_) => self.encapsulate_into_scratch(pk, &mut alloc::vec![0; needed], f),
+            })
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            self.encapsulate_into_scratch(pk, &mut alloc::vec![0; needed], f)
+        }
+    }
+
+    /// Encapsulate into `scratch`, which must hold exactly a ciphertext and a shared secret
+    fn encapsulate_into_scratch<R>(
+        &self,
+        pk: PublicKeyRef,
+        scratch: &mut [u8],
+        f: impl FnOnce(CiphertextRef, SharedSecretRef) -> R,
+    ) -> Result<R> {
+        let kem = unsafe { self.kem.as_ref() };
+        let func = kem.encaps.unwrap();
+        let guard = crate::util::ScrubGuard(scratch);
+        let (ct, ss) = guard.0.split_at_mut(kem.length_ciphertext);
+        let status = unsafe { func(ct.as_mut_ptr(), ss.as_mut_ptr(), pk.bytes.as_ptr()) };
+        status_to_result(status)?;
+        Ok(f(CiphertextRef::new(ct), SharedSecretRef::new(ss)))
+    }
+
+    /// Async encapsulate to the provided public key
+    pub fn async_encapsulate<'a, P: Into<PublicKeyRef<'a>>>(
+        &self,
+        pk: P,
+    ) -> Result<(Ciphertext, SharedSecret)> {
+        let pk = pk.into();
+        self.check_initialized()?;
+        if pk.bytes.len() != self.length_public_key() {
+            return Err(Error::InvalidLength {
+                expected: self.length_public_key(),
+                got: pk.bytes.len(),
+            });
+        }
+        let kem = unsafe { self.kem.as_ref() };
+        let func = kem.async_encaps.unwrap();
+        #[cfg(feature = "std")]
+        let _lock = async_lock();
+        let mut ct = Ciphertext {
+            bytes: self.output_buffer(kem.length_ciphertext),
+        };
+        let mut ss = SharedSecret {
+            bytes: self.output_buffer(kem.length_shared_secret),
+        };
+        // call encapsulate
+        let status = unsafe {
+            func(
+                ct.bytes.as_mut_ptr(),
+                ss.bytes.as_mut_ptr(),
+                pk.bytes.as_ptr(),
+            )
+        };
+        status_to_result(status)?;
+        Ok((ct, ss))
+    }
+
+    /// Begin a split encapsulation, computing the ciphertext before the public key is known
+    ///
+    /// Send [`SplitEncapsulation::ciphertext`] right away and call
+    /// [`SplitEncapsulation::finish`] once the public key arrives. This is the
+    /// preferred form of [`Kem::encapsulate_ciphertext`] and
+    /// [`Kem::encapsulate_shared_secret`], as the handle keeps the ciphertext
+    /// and its ephemeral secret together. See [`Kem::split_encapsulation_security`]
+    /// for the security this flow offers.
+    ///
+    /// Returns [`Error::OperationNotSupported`] if the algorithm has no split encapsulation,
+    /// see [`Kem::length_ephemeral_secret`].
+    pub fn begin_encapsulation(&self) -> Result<SplitEncapsulation> {
+        let (ct, es) = self.encapsulate_ciphertext()?;
+        Ok(SplitEncapsulation {
+            algorithm: self.algorithm,
+            ct,
+            es,
+        })
+    }
+
+
+    /// Encapsulate ciphertext
+    ///
+    /// The ephemeral secret must later be passed to [`Kem::encapsulate_shared_secret`]
+    /// together with this ciphertext; a mismatched pair silently gives a wrong shared
+    /// secret. Unless the two have to be stored separately, use
+    /// [`Kem::begin_encapsulation`], which keeps them together.
+    ///
+    /// Returns [`Error::OperationNotSupported`] if the algorithm has no split encapsulation,
+    /// see [`Kem::length_ephemeral_secret`].
+    pub fn encapsulate_ciphertext(&self) -> Result<(Ciphertext, EphemeralSecret)> {
+        let es_len = self
+            .length_ephemeral_secret()
+            .ok_or(Error::OperationNotSupported)?;
+        let mut ct = Ciphertext {
+            bytes: self.output_buffer(self.length_ciphertext()),
+        };
+        let mut es = EphemeralSecret {
+            bytes: self.output_buffer(es_len),
+        };
+        self.encapsulate_ciphertext_into(&mut ct.bytes, &mut es.bytes)?;
+        Ok((ct, es))
+    }
+
+    /// Compute the ciphertext of a split encapsulation into caller-provided buffers
+    ///
+    /// Like [`Kem::encapsulate_ciphertext`], but `ct` must be [`Kem::length_ciphertext`]
+    /// and `es` [`Kem::length_ephemeral_secret`] bytes long. This does not allocate.
+    pub fn encapsulate_ciphertext_into(&self, ct: &mut [u8], es: &mut [u8]) -> Result<()> {
+        self.check_initialized()?;
+        let es_len = self
+            .length_ephemeral_secret()
+            .ok_or(Error::OperationNotSupported)?;
+        if ct.len() != self.length_ciphertext() {
+            return Err(Error::InvalidCiphertextLength {
+                expected: self.length_ciphertext(),
+                got: ct.len(),
+            });
+        }
+        check_length(es_len, es)?;
+        // call encapsulate_ciphertext
+        let status = unsafe {
+            ffi::OQS_KEM_encaps_ciphertext(self.kem.as_ptr(), ct.as_mut_ptr(), es.as_mut_ptr())
+        };
+        status_to_result(status)
+    }
+
+    /// Compute the shared secret for a ciphertext from [`Kem::encapsulate_ciphertext`]
+    ///
+    /// Returns [`Error::OperationNotSupported`] if the algorithm has no split encapsulation.
+    pub fn encapsulate_shared_secret<
+        'a,
+        P: Into<PublicKeyRef<'a>>,
+        C: Into<CiphertextRef<'a>>,
+        E: Into<EphemeralSecretRef<'a>>,
+    >(
+        &self,
+        ct: C,
+        es: E,
+        pk: P,
+    ) -> Result<SharedSecret> {
+        self.length_ephemeral_secret()
+            .ok_or(Error::OperationNotSupported)?;
+        let mut ss = SharedSecret {
+            bytes: self.output_buffer(self.length_shared_secret()),
+        };
+        self.encapsulate_shared_secret_into(ct, es, pk, &mut ss.bytes)?;
+        Ok(ss)
+    }
+
+    /// Compute the shared secret of a split encapsulation into a caller-provided buffer
+    ///
+    /// Like [`Kem::encapsulate_shared_secret`], but `ss` must be
+    /// [`Kem::length_shared_secret`] bytes long. This does not allocate.
+    pub fn encapsulate_shared_secret_into<
+        'a,
+        P: Into<PublicKeyRef<'a>>,
+        C: Into<CiphertextRef<'a>>,
+        E: Into<EphemeralSecretRef<'a>>,
+    >(
+        &self,
+        ct: C,
+        es: E,
+        pk: P,
+        ss: &mut [u8],
+    ) -> Result<()> {
+        let es_len = self
+            .length_ephemeral_secret()
+            .ok_or(Error::OperationNotSupported)?;
+        let pk = pk.into();
+        self.check_initialized()?;
+        if pk.bytes.len() != self.length_public_key() {
+            return Err(Error::InvalidLength {
+                expected: self.length_public_key(),
+                got: pk.bytes.len(),
+            });
+        }
+        let kem = unsafe { self.kem.as_ref() };
+
+        let ct = ct.into();
+        if ct.bytes.len() != kem.length_ciphertext {
+            return Err(Error::InvalidCiphertextLength {
+                expected: kem.length_ciphertext,
+                got: ct.bytes.len(),
+            });
+        }
+        let es = es.into();
+        if es.bytes.len() != es_len {
+            return Err(Error::InvalidLength {
+                expected: es_len,
+                got: es.bytes.len(),
+            });
+        }
+
+        check_length(kem.length_shared_secret, ss)?;
+
+        let func = kem.encaps_shared_secret.unwrap();
+        // call encapsulate
+        let status = unsafe {
+            func(
+                ss.as_mut_ptr(),
+                ct.bytes.as_ptr(),
+                es.bytes.as_ptr(),
+                pk.bytes.as_ptr(),
+            )
+        };
+        status_to_result(status)
+    }
+
+    /// Decapsulate the provided ciphertext
+    pub fn decapsulate<'a, 'b, S: Into<SecretKeyRef<'a>>, C: Into<CiphertextRef<'b>>>(
+        &self,
+        sk: S,
+        ct: C,
+    ) -> Result<SharedSecret> {
+        let kem = unsafe { self.kem.as_ref() };
+        let sk = sk.into();
+        let ct = ct.into();
+        self.check_decapsulate_input(&sk, &ct)?;
+        let mut ss = SharedSecret {
+            bytes: self.output_buffer(kem.length_shared_secret),
+        };
+        let func = kem.decaps.unwrap();
+        // Call decapsulate
+        let status = unsafe { func(ss.bytes.as_mut_ptr(), ct.bytes.as_ptr(), sk.bytes.as_ptr()) };
+        status_to_result(status)?;
+        self.record_secret_key_use(&sk)?;
+        Ok(ss)
+    }
+
+    /// Decapsulate a batch of ciphertexts with one secret key
+    ///
+    /// The secret key is validated once for the whole batch, and every ciphertext
+    /// length is checked before the first decapsulation. An error for a ciphertext
+    /// is returned as [`Error::Batch`] with the index of the ciphertext; errors
+    /// about the secret key are returned as they are.
+    ///
+    /// With reuse protection, the batch counts as a single use of `sk`.
+    pub fn decapsulate_many<'a, S: Into<SecretKeyRef<'a>>>(
+        &self,
+        sk: S,
+        cts: &[CiphertextRef],
+    ) -> Result<Vec<SharedSecret>> {
+        let sk = sk.into();
+        self.check_initialized()?;
+        self.check_secret_key_length(&sk)?;
+        for (index, ct) in cts.iter().enumerate() {
+            self.check_ciphertext_length(ct)
+                .map_err(|error| Error::batch(index, error))?;
+        }
+        self.check_secret_key_unused(&sk)?;
+
+        let kem = unsafe { self.kem.as_ref() };
+        let func = kem.decaps.unwrap();
+        let mut secrets = Vec::with_capacity(cts.len());
+        for (index, ct) in cts.iter().enumerate() {
+            let mut ss = SharedSecret {
+                bytes: self.output_buffer(kem.length_shared_secret),
+            };
+            let status =
+                unsafe { func(ss.bytes.as_mut_ptr(), ct.bytes.as_ptr(), sk.bytes.as_ptr()) };
+            status_to_result(status).map_err(|error| Error::batch(index, error))?;
+            secrets.push(ss);
+        }
+        self.record_secret_key_use(&sk)?;
+        Ok(secrets)
+    }
+
+    /// Decapsulate the provided ciphertext into a caller-provided buffer
+    ///
+    /// `ss` must be [`Kem::length_shared_secret`] bytes long. This does not allocate.
+    pub fn decapsulate_into<'a, 'b, S: Into<SecretKeyRef<'a>>, C: Into<CiphertextRef<'b>>>(
+        &self,
+        sk: S,
+        ct: C,
+        ss: &mut [u8],
+    ) -> Result<()> {
+        let sk = sk.into();
+        let ct = ct.into();
+        self.check_decapsulate_input(&sk, &ct)?;
+        check_length(self.length_shared_secret(), ss)?;
+        let kem = unsafe { self.kem.as_ref() };
+        let func = kem.decaps.unwrap();
+        let status = unsafe { func(ss.as_mut_ptr(), ct.bytes.as_ptr(), sk.bytes.as_ptr()) };
+        status_to_result(status)?;
+        if let Err(e) = self.record_secret_key_use(&sk) {
+            crate::util::scrub(ss);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Decapsulate by calling liboqs directly on raw pointers
+    ///
+    /// An escape hatch to measure the overhead of the safe API, such as in the
+    /// `throughput` example; use [`Kem::decapsulate_into`] everywhere else.
+    /// Nothing is checked: not the lengths, not [`Kem::init`], not reuse
+    /// protection and no decapsulation failures are counted with the `metrics` feature.
+    ///
+    /// # Safety
+    ///
+    /// `ss_ptr` must be valid for writes of [`Kem::length_shared_secret`] bytes,
+    /// `ct_ptr` valid for reads of [`Kem::length_ciphertext`] bytes and `sk_ptr`
+    /// valid for reads of [`Kem::length_secret_key`] bytes, none overlapping
+    /// `ss_ptr`. Algorithms that [require initialization](Algorithm::requires_init)
+    /// must be initialized; liboqs may crash otherwise.
+    pub unsafe fn decapsulate_raw(
+        &self,
+        ss_ptr: *mut u8,
+        ct_ptr: *const u8,
+        sk_ptr: *const u8,
+    ) -> Result<()> {
+        let func = self.kem.as_ref().decaps.unwrap();
+        status_to_result(func(ss_ptr, ct_ptr, sk_ptr))
+    }
+
+    /// Decapsulate the provided ciphertext into a fixed-size array
+    ///
+    /// Returns [`Error::InvalidLength`] if `N` is not [`Kem::length_shared_secret`].
+    /// This avoids the allocation of [`Kem::decapsulate`]; the array is scrubbed
+    /// if decapsulation fails.
+    pub fn decapsulate_array<
+        'a,
+        'b,
+        S: Into<SecretKeyRef<'a>>,
+        C: Into<CiphertextRef<'b>>,
+        const N: usize,
+    >(
+        &self,
+        sk: S,
+        ct: C,
+    ) -> Result<[u8; N]> {
+        if N != self.length_shared_secret() {
+            return Err(Error::InvalidLength {
+                expected: self.length_shared_secret(),
+                got: N,
+            });
+        }
+        let mut ss = [0u8; N];
+        match self.decapsulate_into(sk, ct, &mut ss) {
+            Ok(()) => Ok(ss),
+            Err(e) => {
+                crate::util::scrub(&mut ss);
+                Err(e)
+            }
+        }
+    }
+
+    /// Decapsulate and compare the shared secret to `expected` in constant time
+    ///
+    /// The derived shared secret is scrubbed before returning and is never
+    /// handed to the caller. A result of `Ok(false)` means the secret did not
+    /// match, i.e. the encapsulation is rejected; errors are only returned
+    /// for invalid input lengths or a failing decapsulation.
+    ///
+    /// With the `metrics` feature, rejections are counted in `Kem::rejection_count`.
+    pub fn decapsulate_and_confirm<
+        'a,
+        'b,
+        S: Into<SecretKeyRef<'a>>,
+        C: Into<CiphertextRef<'b>>,
+    >(
+        &self,
+        sk: S,
+        ct: C,
+        expected: &[u8],
+    ) -> Result<bool> {
+        let confirmed = self.decapsulate_then(sk, ct, |ss| crate::util::ct_eq(ss, expected))?;
+        #[cfg(feature = "metrics")]
+        self.rejections
+            .fetch_add(u64::from(!confirmed), Ordering::Relaxed);
+        Ok(confirmed)
+    }
+
+    /// Decapsulate with each of `sks` and find the one whose shared secret is `expected`
+    ///
+    /// Meant for key rotation, when ciphertexts may be encapsulated to the old or
+    /// the new public key. Decapsulation with the wrong key does not fail, as
+    /// IND-CCA KEMs reject implicitly, so the right key can only be told apart
+    /// with a confirmation the caller supplies: each shared secret is compared to
+    /// `expected` in constant time, as in [`Kem::decapsulate_and_confirm`].
+    /// Returns the index of the first matching key, or `None` if no key matches.
+    ///
+    /// Every key is tried, also after a match, so the time taken does not reveal
+    /// which key matched. Errors for a key, including a wrong length, are returned
+    /// as [`Error::Batch`] with its index; all lengths are checked before the first
+    /// decapsulation. With reuse protection, every key in `sks` counts as used.
+    /// With the `metrics` feature, a `None` result counts as one rejection.
+    pub fn decapsulate_any<'a, C: Into<CiphertextRef<'a>>>(
+        &self,
+        sks: &[SecretKeyRef],
+        ct: C,
+        expected: &[u8],
+    ) -> Result<Option<usize>> {
+        let ct = ct.into();
+        self.check_initialized()?;
+        self.check_ciphertext_length(&ct)?;
+        for (index, sk) in sks.iter().enumerate() {
+            self.check_secret_key_length(sk)
+                .map_err(|error| Error::batch(index, error))?;
+        }
+        let mut matched = None;
+        for (index, &sk) in sks.iter().enumerate() {
+            let confirmed = self
+                .decapsulate_then(sk, ct, |ss| crate::util::ct_eq(ss, expected))
+                .map_err(|error| Error::batch(index, error))?;
+            if confirmed && matched.is_none() {
+                matched = Some(index);
+            }
+        }
+        #[cfg(feature = "metrics")]
+        self.rejections
+            .fetch_add(u64::from(matched.is_none()), Ordering::Relaxed);
+        Ok(matched)
+    }
+
+    /// Number of rejected encapsulations seen by this `Kem`
+    ///
+    /// IND-CCA KEMs reject invalid ciphertexts implicitly: decapsulation succeeds
+    /// with a pseudorandom shared secret, and liboqs does not report the rejection.
+    /// A rejection only becomes visible once the shared secret is confirmed, so this
+    /// counts the calls to [`Kem::decapsulate_and_confirm`] that returned `Ok(false)`.
+    /// A rising rate may indicate a chosen-ciphertext attack or a protocol bug.
+    ///
+    /// The count is an aggregate over all calls and does not reveal which call
+    /// was rejected; it is updated without branching on secret data.
+    #[cfg(feature = "metrics")]
+    pub fn rejection_count(&self) -> u64 {
+        self.rejections.load(Ordering::Relaxed)
+    }
+
+    /// Decapsulate the provided ciphertext and pass the shared secret to `f`
+    ///
+    /// The shared secret is written to a stack buffer that is scrubbed before
+    /// this method returns, also if `f` panics, so it never has to be owned
+    /// by the caller. This is convenient to feed it straight into a KDF.
+    pub fn decapsulate_then<'a, 'b, S: Into<SecretKeyRef<'a>>, C: Into<CiphertextRef<'b>>, R>(
+        &self,
+        sk: S,
+        ct: C,
+        f: impl FnOnce(&[u8]) -> R,
+    ) -> Result<R> {
+        let kem = unsafe { self.kem.as_ref() };
+        let sk = sk.into();
+        let ct = ct.into();
+        self.check_decapsulate_input(&sk, &ct)?;
+        let mut buf = [0u8; DECAPSULATE_THEN_STACK_SIZE];
+        let mut heap;
+        let ss = if kem.length_shared_secret <= buf.len() {
+            &mut buf[..kem.length_shared_secret]
+        } else {
+            heap = alloc::vec![0; kem.length_shared_secret];
+            &mut heap[..]
+        };
+        let guard = crate::util::ScrubGuard(ss);
+        let func = kem.decaps.unwrap();
+        let status = unsafe { func(guard.0.as_mut_ptr(), ct.bytes.as_ptr(), sk.bytes.as_ptr()) };
+        status_to_result(status)?;
+        self.record_secret_key_use(&sk)?;
+        Ok(f(guard.0))
+    }
+
+    /// Decapsulate and derive `out` from the shared secret with HKDF-SHA256
+    ///
+    /// Runs HKDF-Extract with `salt` and the shared secret, then HKDF-Expand with
+    /// `info` to fill `out` (RFC 5869). The shared secret and the pseudorandom key
+    /// only live in scrubbed scratch buffers, see [`Kem::decapsulate_then`], so the
+    /// raw secret never leaves this crate. Requires the `hkdf` feature.
+    ///
+    /// As the KEMs take no context string, bind the protocol and its transcript
+    /// in `info` to separate protocols that share a key pair.
+    ///
+    /// # Panics
+    /// If `out` is longer than 255 * 32 bytes, the maximum HKDF-Expand output length.
+    #[cfg(feature = "hkdf")]
+    pub fn decapsulate_hkdf<'a, 'b, S: Into<SecretKeyRef<'a>>, C: Into<CiphertextRef<'b>>>(
+        &self,
+        sk: S,
+        ct: C,
+        salt: &[u8],
+        info: &[u8],
+        out: &mut [u8],
+    ) -> Result<()> {
+        self.decapsulate_then(sk, ct, |ss| {
+            hkdf_expand(&hkdf_extract(salt, ss)[..], info, out)
+        })
+    }
+
+    /// Encapsulate on the [`executor`](crate::executor), without blocking the caller
+    ///
+    /// The returned future resolves to the result of [`Kem::encapsulate`].
+    /// Unlike [`Kem::async_encapsulate`], this does not use precomputed state.
+    #[cfg(feature = "async")]
+    pub fn encapsulate_async(
+        self: &Arc<Self>,
+        pk: PublicKey,
+    ) -> impl core::future::Future<Output = Result<(Ciphertext, SharedSecret)>> {
+        let kem = Arc::clone(self);
+        crate::executor::spawn(move || kem.encapsulate(&pk))
+    }
+
+    /// Decapsulate on the [`executor`](crate::executor), without blocking the caller
+    ///
+    /// The returned future resolves to the result of [`Kem::decapsulate`].
+    #[cfg(feature = "async")]
+    pub fn decapsulate_async(
+        self: &Arc<Self>,
+        sk: SecretKey,
+        ct: Ciphertext,
+    ) -> impl core::future::Future<Output = Result<SharedSecret>> {
+        let kem = Arc::clone(self);
+        crate::executor::spawn(move || kem.decapsulate(&sk, &ct))
+    }
+
+    /// Run a keypair, encapsulate and decapsulate with a fresh key pair
+    ///
+    /// Returns the shared secrets from encapsulation and decapsulation,
+    /// in that order, for the caller to compare.
+    /// See [`Kem::self_test`] for a version that checks them itself.
+    pub fn roundtrip(&self) -> Result<(SharedSecret, SharedSecret)> {
+        let (pk, sk) = self.keypair()?;
+        let (ct, encaps_ss) = self.encapsulate(&pk)?;
+        let decaps_ss = self.decapsulate(&sk, &ct)?;
+        Ok((encaps_ss, decaps_ss))
+    }
+
+    /// Check that a fresh key pair decapsulates to the encapsulated shared secret
+    ///
+    /// Returns [`Error::PairwiseConsistencyFailed`] if the shared secrets differ.
+    pub fn self_test(&self) -> Result<()> {
+        let (encaps_ss, decaps_ss) = self.roundtrip()?;
+        if encaps_ss == decaps_ss {
+            Ok(())
+        } else {
+            Err(Error::PairwiseConsistencyFailed)
+        }
+    }
+
+    /// Warm up the implementation before it is first needed
+    ///
+    /// Calls [`Kem::init`] and performs one throwaway keypair, encapsulation and
+    /// decapsulation, so that lazy initialization and the page faults on large
+    /// static tables do not slow down the first real request. This costs a full
+    /// roundtrip and is meant to be called once at startup.
+    /// The generated keys and secrets are scrubbed.
+    pub fn warmup(&self) -> Result<()> {
+        self.init()?;
+        let (pk, mut sk) = self.keypair()?;
+        let sk = crate::util::ScrubGuard(&mut sk.bytes);
+        let (ct, mut ss) = self.encapsulate(&pk)?;
+        let _ss = crate::util::ScrubGuard(&mut ss.bytes);
+        self.decapsulate_then(SecretKeyRef::new(sk.0), &ct, |_| ())
+    }
+
+    /// Allocate a buffer for liboqs output, on the NUMA node of this `Kem` if it has one
+    ///
+    /// Inside [`scratch::with_arena`], a recycled buffer is used if there is one.
+    fn output_buffer(&self, len: usize) -> Vec<u8> {
+        #[cfg(feature = "std")]
+        #[allow(unused_mut)]
+        let mut buf = scratch::take(len).unwrap_or_else(|| alloc::vec![0; len]);
+        #[cfg(not(feature = "std"))]
+        #[allow(unused_mut)]
+        let mut buf = alloc::vec![0; len];
+        #[cfg(feature = "numa")]
+        if let Some(node) = self.numa_node {
+            crate::numa::bind(&mut buf, node);
+        }
+        buf
+    }
+
+    /// Return [`Error::NotInitialized`] if the algorithm needs [`Kem::init`] and it was not called
+    fn check_initialized(&self) -> Result<()> {
+        if self.algorithm.requires_init() && !self.initialized.load(Ordering::SeqCst) {
+            return Err(Error::NotInitialized);
+        }
+        Ok(())
+    }
+
+    /// Check the secret key and ciphertext lengths for decapsulation
+    ///
+    /// Also returns [`Error::NotInitialized`] if the `Kem` needs to be initialized first.
+    /// With reuse protection (see [`KemBuilder::reuse_protection`]), this also
+    /// returns [`Error::KeyReused`] if `sk` was used before. The use is only
+    /// recorded by [`Kem::record_secret_key_use`] once liboqs has succeeded.
+    fn check_decapsulate_input(&self, sk: &SecretKeyRef, ct: &CiphertextRef) -> Result<()> {
+        self.check_initialized()?;
+        self.check_secret_key_length(sk)?;
+        self.check_ciphertext_length(ct)?;
+        self.check_secret_key_unused(sk)
+    }
+
+    fn check_secret_key_length(&self, sk: &SecretKeyRef) -> Result<()> {
+        if sk.bytes.len() != self.length_secret_key() {
+            return Err(Error::InvalidSecretKeyLength {
+                expected: self.length_secret_key(),
+                got: sk.bytes.len(),
+            });
+        }
+        Ok(())
+    }
+
+    fn check_ciphertext_length(&self, ct: &CiphertextRef) -> Result<()> {
+        if ct.bytes.len() != self.length_ciphertext() {
+            return Err(Error::InvalidCiphertextLength {
+                expected: self.length_ciphertext(),
+                got: ct.bytes.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Return [`Error::KeyReused`] if reuse protection is on and `sk` was used before
+    fn check_secret_key_unused(&self, sk: &SecretKeyRef) -> Result<()> {
+        #[cfg(feature = "std")]
+        if let Some(used) = &self.used_secret_keys {
+            let used = used.lock().unwrap_or_else(|e| e.into_inner());
+            if used.contains(&crate::util::fingerprint(sk.bytes)) {
+                return Err(Error::KeyReused);
+            }
+        }
+        #[cfg(not(feature = "std"))]
+        let _ = sk;
+        Ok(())
+    }
+
+    /// Record a successful decapsulation with `sk` for reuse protection
+    ///
+    /// Returns [`Error::KeyReused`] if another thread recorded a use of `sk`
+    /// since [`Kem::check_secret_key_unused`], so only one of them succeeds.
+    /// The caller must not hand out the shared secret then.
+    fn record_secret_key_use(&self, sk: &SecretKeyRef) -> Result<()> {
+        #[cfg(feature = "std")]
+        if let Some(used) = &self.used_secret_keys {
+            let mut used = used.lock().unwrap_or_else(|e| e.into_inner());
+            if !used.insert(crate::util::fingerprint(sk.bytes)) {
+                return Err(Error::KeyReused);
+            }
+        }
+        #[cfg(not(feature = "std"))]
+        let _ = sk;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The `count = 0` entry of `tests/kats/Kyber768.rsp`, as pk, sk, ct and ss
+    #[cfg(all(feature = "kyber", any(feature = "hybrid", feature = "hkdf")))]
+    pub(super) fn kyber768_kat() -> [Vec<u8>; 4] {
+        let rsp = include_str!("../tests/kats/Kyber768.rsp");
+        let field = |name: &str| -> Vec<u8> {
+            let hex = rsp
+                .lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix(" = "))
+                .unwrap();
+            (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+                .collect()
+        };
+        [field("pk"), field("sk"), field("ct"), field("ss")]
+    }
+
+    #[test]
+    fn test_recommended() {
+        assert!(!recommended().is_empty());
+        for alg in recommended() {
+            assert!(!alg.is_cryptographically_broken(), "{:?}", alg);
+            assert_ne!(alg.family(), Family::Csidh);
+        }
+    }
+
+    #[test]
+    fn test_ml_kem_equivalent() {
+        assert_eq!(Algorithm::Kyber768.ml_kem_equivalent(), Some("ML-KEM-768"));
+        assert_eq!(Algorithm::Kyber768_90s.ml_kem_equivalent(), None);
+        for alg in all() {
+            if alg.ml_kem_equivalent().is_some() {
+                assert_eq!(alg.family(), Family::Kyber);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cryptographically_broken() {
+        for alg in all() {
+            let prefix = &alg.name()[..4];
+            let isogeny =
+                prefix.eq_ignore_ascii_case("SIDH") || prefix.eq_ignore_ascii_case("SIKE");
+            assert_eq!(alg.is_cryptographically_broken(), isogeny, "{:?}", alg);
+        }
+    }
+
+    #[test]
+    fn test_variant_predicates() {
+        for alg in all() {
+            assert_eq!(
+                alg.is_compressed(),
+                alg.name().contains("compressed"),
+                "{:?}",
+                alg
+            );
+            assert_eq!(alg.is_1cca(), alg.name().contains("1cca"), "{:?}", alg);
+        }
+        assert!(Algorithm::SikeP434Compressed1CCA.is_compressed());
+        assert!(Algorithm::SikeP434Compressed1CCA.is_1cca());
+        assert!(!Algorithm::SikeP434.is_compressed());
+        assert!(!Algorithm::SikeP434Compressed.is_1cca());
+    }
+
+    #[test]
+    #[cfg(all(feature = "kyber", feature = "ntru"))]
+    fn test_public_from_secret_support() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber768)?;
+        let (pk, sk) = kem.keypair()?;
+        assert_eq!(kem.public_from_secret(&sk)?, pk);
+
+        let kem = Kem::new(Algorithm::NtruHps2048509)?;
+        let (_, sk) = kem.keypair()?;
+        assert!(matches!(
+            kem.public_from_secret(&sk),
+            Err(Error::OperationNotSupported)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "classic_mceliece")]
+    fn test_exact_capacity() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::ClassicMcEliece348864)?;
+        let (pk, sk) = kem.keypair()?;
+        let (ct, ss) = kem.encapsulate(&pk)?;
+        let ss2 = kem.decapsulate(&sk, &ct)?;
+        assert_eq!(pk.bytes.capacity(), kem.length_public_key());
+        assert_eq!(sk.bytes.capacity(), kem.length_secret_key());
+        assert_eq!(ct.bytes.capacity(), kem.length_ciphertext());
+        assert_eq!(ss.bytes.capacity(), kem.length_shared_secret());
+        assert_eq!(ss2.bytes.capacity(), kem.length_shared_secret());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(feature = "kyber", feature = "std"))]
+    fn test_concurrent_decapsulate() -> Result<()> {
+        use std::sync::Arc;
+        crate::init();
+        let kem = Arc::new(Kem::new(Algorithm::Kyber768)?);
+        kem.init()?;
+        let (pk, sk) = kem.keypair()?;
+        let sk = Arc::new(sk);
+        let encapsulations = Arc::new(
+            (0..32)
+                .map(|_| kem.encapsulate(&pk))
+                .collect::<Result<Vec<_>>>()?,
+        );
+        let threads: Vec<_> = (0..16)
+            .map(|i| {
+                let (kem, sk, encapsulations) = (kem.clone(), sk.clone(), encapsulations.clone());
+                std::thread::spawn(move || {
+                    for j in 0..256 {
+                        let (ct, ss) = &encapsulations[(i + j) % encapsulations.len()];
+                        assert_eq!(&kem.decapsulate(&*sk, ct).unwrap(), ss);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(feature = "kyber", feature = "std"))]
+    fn test_into_does_not_allocate() -> Result<()> {
+        use crate::alloc_counter::count_allocations;
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (pk, sk) = kem.keypair()?;
+        let mut ct = [0u8; 768];
+        let mut ss = [0u8; 32];
+        let mut ss2 = [0u8; 32];
+
+        let (result, allocations) =
+            count_allocations(|| kem.encapsulate_into(&pk, &mut ct, &mut ss));
+        result?;
+        assert_eq!(allocations, 0, "encapsulate_into allocated");
+
+        let ct = kem.ciphertext_from_bytes(&ct).unwrap();
+        let (result, allocations) = count_allocations(|| kem.decapsulate_into(&sk, ct, &mut ss2));
+        result?;
+        assert_eq!(allocations, 0, "decapsulate_into allocated");
+        assert_eq!(ss, ss2);
+
+        // Make sure the counter itself works
+        let (_, allocations) = count_allocations(|| kem.decapsulate(&sk, ct));
+        assert_eq!(allocations, 1);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(feature = "kyber", feature = "zeroize"))]
+    fn test_zeroize_and_reset() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (pk, sk) = kem.keypair()?;
+        let (ct, mut ss) = kem.encapsulate(&pk)?;
+        let expected = ss.clone();
+        let capacity = ss.bytes.capacity();
+        ss.zeroize_and_reset();
+        assert!(ss.bytes.iter().all(|&b| b == 0));
+        assert_eq!(ss.len(), kem.length_shared_secret());
+        assert_eq!(ss.bytes.capacity(), capacity);
+        kem.decapsulate_into(&sk, &ct, ss.as_mut_bytes())?;
+        assert_eq!(ss, expected);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_deserialize_and_new() {
+        use alloc::format;
+        use serde::de::value::{Error as ValueError, StrDeserializer};
+        use serde::de::IntoDeserializer;
+        crate::init();
+        for alg in all() {
+            let name = format!("{:?}", alg);
+            let deserializer: StrDeserializer<ValueError> = name.as_str().into_deserializer();
+            match Kem::deserialize_and_new(deserializer) {
+                Ok(kem) => assert_eq!(kem.algorithm(), *alg),
+                Err(e) => {
+                    assert!(!alg.is_enabled());
+                    assert!(format!("{}", e).contains(alg.name()));
+                }
+            }
+        }
+        let deserializer: StrDeserializer<ValueError> = "NotAnAlgorithm".into_deserializer();
+        assert!(Kem::deserialize_and_new(deserializer).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_decapsulate_array() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (pk, sk) = kem.keypair()?;
+        let (ct, ss) = kem.encapsulate(&pk)?;
+        let array: [u8; 32] = kem.decapsulate_array(&sk, &ct)?;
+        assert_eq!(&array[..], ss.as_ref());
+        assert!(matches!(
+            kem.decapsulate_array::<_, _, 16>(&sk, &ct),
+            Err(Error::InvalidLength {
+                expected: 32,
+                got: 16
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(feature = "kyber", feature = "frodokem"))]
+    fn test_secret_key_ct_eq() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (_, sk) = kem.keypair()?;
+        let (_, other) = kem.keypair()?;
+        let sk_ref = SecretKeyRef::from(&sk);
+        assert!(sk_ref.ct_eq(&sk));
+        assert!(sk_ref.ct_eq(&sk.clone()));
+        assert!(!sk_ref.ct_eq(&other));
+
+        let mut flipped = sk.clone().into_vec();
+        flipped[0] ^= 1;
+        assert!(!sk_ref.ct_eq(kem.try_secret_key_ref(&flipped)?));
+
+        let frodo = Kem::new(Algorithm::FrodoKem640Aes)?;
+        let (_, frodo_sk) = frodo.keypair()?;
+        assert!(!sk_ref.ct_eq(&frodo_sk));
+        assert!(!SecretKeyRef::from(&frodo_sk).ct_eq(&sk));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(feature = "kyber", feature = "async"))]
+    fn test_async_roundtrip() -> Result<()> {
+        use crate::executor::test::{block_on, EXECUTOR_TEST_LOCK};
+        let _guard = EXECUTOR_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        crate::init();
+        let kem = Arc::new(Kem::new(Algorithm::Kyber512)?);
+        let (pk, sk) = kem.keypair()?;
+        let (ct, ss) = block_on(kem.encapsulate_async(pk))?;
+        assert_eq!(block_on(kem.decapsulate_async(sk.clone(), ct))?, ss);
+
+        let bad_ct = Ciphertext { bytes: Vec::new() };
+        assert!(matches!(
+            block_on(kem.decapsulate_async(sk, bad_ct)),
+            Err(Error::InvalidCiphertextLength { got: 0, .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(feature = "kyber", feature = "hex"))]
+    fn test_hex_display() -> Result<()> {
+        use alloc::string::ToString;
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (pk, _) = kem.keypair()?;
+        let (ct, _) = kem.encapsulate(&pk)?;
+        let hex = pk.to_string();
+        assert_eq!(hex.len(), 2 * pk.len());
+        assert_eq!(PublicKeyRef::from(&pk).to_string(), hex);
+        assert!(hex.bytes().all(|c| matches!(c, b'0'..=b'9' | b'a'..=b'f')));
+        assert_eq!(ct.to_string().len(), 2 * ct.len());
+        assert_eq!(CiphertextRef::from(&ct).to_string(), ct.to_string());
+
+        let pk = PublicKey {
+            bytes: alloc::vec![0x00, 0x0f, 0xab, 0xff],
+        };
+        assert_eq!(pk.to_string(), "000fabff");
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(feature = "sike", feature = "kyber"))]
+    fn test_requires_init() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::SikeP434)?;
+        assert!(Algorithm::SikeP434.requires_init());
+        let (pk, sk) = kem.keypair()?;
+        assert!(matches!(kem.encapsulate(&pk), Err(Error::NotInitialized)));
+
+        kem.init()?;
+        let (ct, ss) = kem.encapsulate(&pk)?;
+        assert_eq!(kem.decapsulate(&sk, &ct)?, ss);
+
+        kem.deinit()?;
+        assert!(matches!(kem.encapsulate(&pk), Err(Error::NotInitialized)));
+        assert!(matches!(
+            kem.decapsulate(&sk, &ct),
+            Err(Error::NotInitialized)
+        ));
+
+        // Algorithms without precomputation count as initialized
+        assert!(!Algorithm::Kyber512.requires_init());
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (pk, _) = kem.keypair()?;
+        kem.encapsulate(&pk)?;
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_one_shot_secret_key() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (pk, sk) = kem.keypair()?;
+        let (ct, ss) = kem.encapsulate(&pk)?;
+        let sk = OneShotSecretKey::new(sk);
+        assert_eq!(alloc::format!("{:?}", sk), "OneShotSecretKey { .. }");
+        assert_eq!(sk.decapsulate(&kem, &ct)?, ss);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "sike")]
+    fn test_rekey() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::SikeP434)?;
+        let (pk1, sk1) = kem.rekey()?;
+        let (pk2, sk2) = kem.rekey()?;
+        assert_ne!(pk1, pk2);
+        for (pk, sk) in [(pk1, sk1), (pk2, sk2)].iter() {
+            let (ct, ss) = kem.encapsulate(pk)?;
+            assert_eq!(kem.decapsulate(sk, &ct)?, ss);
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "sike")]
+    fn test_ephemeral_secret_storage() -> Result<()> {
+        fn assert_send<T: Send>() {}
+        assert_send::<EphemeralSecret>();
+
+        crate::init();
+        let kem = Kem::new(Algorithm::SikeP434Compressed)?;
+        kem.init()?;
+        let (pk, sk) = kem.keypair()?;
+        let (ct, es) = kem.encapsulate_ciphertext()?;
+        let stored = es.to_bytes();
+        drop(es);
+
+        let es = kem.try_ephemeral_secret_ref(&stored)?;
+        let ss = kem.encapsulate_shared_secret(&ct, es, &pk)?;
+        assert_eq!(kem.decapsulate(&sk, &ct)?, ss);
+        let es = kem.ephemeral_secret_from_bytes(&stored).unwrap();
+        assert_eq!(kem.encapsulate_shared_secret(&ct, es, &pk)?, ss);
+        assert!(kem.ephemeral_secret_from_bytes(&stored[1..]).is_none());
+        assert!(matches!(
+            kem.try_ephemeral_secret_ref(&stored[1..]),
+            Err(Error::InvalidLength { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_decapsulate_raw() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (pk, sk) = kem.keypair()?;
+        let (ct, ss) = kem.encapsulate(&pk)?;
+        let mut out = alloc::vec![0u8; kem.length_shared_secret()];
+        unsafe {
+            kem.decapsulate_raw(out.as_mut_ptr(), ct.as_ref().as_ptr(), sk.as_ref().as_ptr())?
+        };
+        assert_eq!(out, ss.as_ref());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_public_key_crc() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (pk, _) = kem.keypair()?;
+        let mut buf = pk.with_crc();
+        assert_eq!(kem.public_key_from_bytes_with_crc(&buf)?.to_owned(), pk);
+        buf[0] ^= 1;
+        assert!(matches!(
+            kem.public_key_from_bytes_with_crc(&buf),
+            Err(Error::ChecksumMismatch)
+        ));
+        assert!(matches!(
+            kem.public_key_from_bytes_with_crc(&buf[1..]),
+            Err(Error::InvalidLength { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_roundtrip() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (encaps_ss, decaps_ss) = kem.roundtrip()?;
+        assert_eq!(encaps_ss, decaps_ss);
+        kem.self_test()
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_from_config_str() -> Result<()> {
+        crate::init();
+        assert_eq!(
+            Kem::from_config_str("kyber-768")?.algorithm(),
+            Algorithm::Kyber768
+        );
+        match Kem::from_config_str("Kyber769") {
+            Err(Error::AlgorithmUnavailable {
+                error,
+                alternatives,
+            }) => {
+                assert!(matches!(*error, Error::InvalidFormat));
+                assert_eq!(alternatives, safe_enabled());
+                assert!(alternatives.contains(&Algorithm::Kyber768));
+            }
+            _ => panic!("Kyber769 is not an algorithm"),
+        }
+        let message = alloc::format!("{}", Kem::from_config_str("Kyber769").err().unwrap());
+        assert!(message.starts_with("OQS Error: Invalid format; enabled alternatives: "));
+        for algorithm in all().iter().filter(|alg| !alg.is_enabled()) {
+            match Kem::from_config_str(algorithm.name()) {
+                Err(Error::AlgorithmUnavailable { error, .. }) => assert!(matches!(
+                    *error,
+                    Error::AlgorithmDisabled | Error::MissingDependency(_)
+                )),
+                _ => panic!("{:?} is not enabled", algorithm),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(feature = "sike", feature = "kyber", feature = "std"))]
+    fn test_time_init() -> Result<()> {
+        crate::init();
+        assert_eq!(Kem::new(Algorithm::Kyber512)?.time_init()?, None);
+        let kem = Kem::new(Algorithm::SikeP434)?;
+        // Other tests may hold initialized `Kem`s at the same time
+        match kem.time_init() {
+            Ok(elapsed) => assert!(elapsed.is_some()),
+            Err(Error::OperationNotSupported) => {}
+            Err(error) => return Err(error),
+        }
+        kem.init()?;
+        assert!(matches!(kem.time_init(), Err(Error::OperationNotSupported)));
+        kem.deinit()?;
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "sike")]
+    fn test_begin_encapsulation() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::SikeP434Compressed)?;
+        kem.init()?;
+        let (pk, sk) = kem.keypair()?;
+        let split = kem.begin_encapsulation()?;
+        let ct = split.ciphertext().clone();
+        assert_eq!(
+            alloc::format!("{:?}", split),
+            alloc::format!(
+                "SplitEncapsulation {{ algorithm: SikeP434Compressed, ct: {:?}, .. }}",
+                ct
+            )
+        );
+        let ss = split.finish(&kem, &pk)?;
+        assert_eq!(kem.decapsulate(&sk, &ct)?, ss);
+
+        let other = Kem::new(Algorithm::SikeP503Compressed)?;
+        assert!(matches!(
+            kem.begin_encapsulation()?.finish(&other, &pk),
+            Err(Error::AlgorithmMismatch)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_begin_encapsulation_not_supported() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        assert!(matches!(
+            kem.begin_encapsulation(),
+            Err(Error::OperationNotSupported)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "sike")]
+    fn test_split_encapsulation_into() -> Result<()> {
+        use alloc::vec;
+        crate::init();
+        let kem = Kem::new(Algorithm::SikeP434Compressed)?;
+        kem.init()?;
+        let (pk, sk) = kem.keypair()?;
+        let es_len = kem.length_ephemeral_secret().unwrap();
+        let mut ct = vec![0; kem.length_ciphertext()];
+        let mut es = vec![0; es_len];
+        let mut ss = vec![0; kem.length_shared_secret()];
+        for _ in 0..2 {
+            kem.encapsulate_ciphertext_into(&mut ct, &mut es)?;
+            let ct = kem.try_ciphertext_ref(&ct)?;
+            let es = kem.try_ephemeral_secret_ref(&es)?;
+            kem.encapsulate_shared_secret_into(ct, es, &pk, &mut ss)?;
+            assert_eq!(kem.decapsulate(&sk, ct)?.as_ref(), &ss[..]);
+        }
+
+        assert!(matches!(
+            kem.encapsulate_ciphertext_into(&mut ct[1..], &mut es),
+            Err(Error::InvalidCiphertextLength { .. })
+        ));
+        assert!(matches!(
+            kem.encapsulate_ciphertext_into(&mut ct, &mut es[1..]),
+            Err(Error::InvalidLength { .. })
+        ));
+        let (ct, es) = kem.encapsulate_ciphertext()?;
+        assert!(matches!(
+            kem.encapsulate_shared_secret_into(&ct, &es, &pk, &mut ss[1..]),
+            Err(Error::InvalidLength { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_uses_fips_primitives() -> Result<()> {
+        crate::init();
+        assert!(Kem::new(Algorithm::Kyber768_90s)?.uses_fips_primitives());
+        assert!(!Kem::new(Algorithm::Kyber768)?.uses_fips_primitives());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_keypair_struct() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let keypair = kem.keypair_struct()?;
+        let (ct, ss) = kem.encapsulate(&keypair.public)?;
+        assert_eq!(kem.decapsulate(&keypair.secret, &ct)?, ss);
+
+        let debug = alloc::format!("{:?}", keypair);
+        assert!(debug.starts_with("KeyPair { public: "));
+        assert!(!debug.contains("secret"));
+
+        let expected = keypair.clone();
+        let (pk, sk) = keypair.into_parts();
+        assert_eq!(pk, expected.public);
+        assert_eq!(sk, expected.secret);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(feature = "kyber", feature = "serde"))]
+    fn test_keypair_serde_roundtrip() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let keypair = kem.keypair_struct()?;
+        let json = serde_json::to_string(&keypair).unwrap();
+        assert!(json.starts_with("{\"public\":"));
+        let decoded: KeyPair = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, keypair);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "hkdf")]
+    fn test_expand_rfc5869() {
+        // RFC 5869, test case 1
+        let prk = [
+            0x07, 0x77, 0x09, 0x36, 0x2c, 0x2e, 0x32, 0xdf, 0x0d, 0xdc, 0x3f, 0x0d, 0xc4, 0x7b,
+            0xba, 0x63, 0x90, 0xb6, 0xc7, 0x3b, 0xb5, 0x0f, 0x9c, 0x31, 0x22, 0xec, 0x84, 0x4a,
+            0xd7, 0xc2, 0xb3, 0xe5,
+        ];
+        let info = [0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9];
+        let okm = [
+            0x3c, 0xb2, 0x5f, 0x25, 0xfa, 0xac, 0xd5, 0x7a, 0x90, 0x43, 0x4f, 0x64, 0xd0, 0x36,
+            0x2f, 0x2a, 0x2d, 0x2d, 0x0a, 0x90, 0xcf, 0x1a, 0x5a, 0x4c, 0x5d, 0xb0, 0x2d, 0x56,
+            0xec, 0xc4, 0xc5, 0xbf, 0x34, 0x00, 0x72, 0x08, 0xd5, 0xb8, 0x87, 0x18, 0x58, 0x65,
+        ];
+        let ss = SharedSecret {
+            bytes: prk.to_vec(),
+        };
+        assert_eq!(ss.expand(&info, okm.len())[..], okm[..]);
+        assert_eq!(ss.expand(&info, 10)[..], okm[..10]);
+        assert!(ss.expand(&info, 0).is_empty());
+        assert_ne!(ss.expand(b"other", 10)[..], okm[..10]);
+    }
+
+    #[test]
+    #[cfg(feature = "kdf")]
+    fn test_combine() {
+        // Computed with Python's hashlib.sha3_256
+        let expected = [
+            0x21, 0x36, 0x47, 0x46, 0x7f, 0xfb, 0xd8, 0xdc, 0x93, 0x17, 0x03, 0xfd, 0x4f, 0x7c,
+            0x04, 0x59, 0x90, 0xcc, 0x22, 0x44, 0x58, 0xe1, 0x58, 0xc8, 0x1e, 0x4a, 0xb1, 0x7c,
+            0xb5, 0x99, 0x04, 0x44,
+        ];
+        let first = SharedSecret {
+            bytes: (0..32).collect(),
+        };
+        let second = SharedSecret {
+            bytes: (32..64).collect(),
+        };
+        assert_eq!(first.combine(&second, b"\\.//^\\")[..], expected[..]);
+        assert_ne!(second.combine(&first, b"\\.//^\\")[..], expected[..]);
+        assert_ne!(first.combine(&second, b"other")[..], expected[..]);
     }
 
-    /// Get the length of the secret key
-    pub fn length_secret_key(&self) -> usize {
-        let kem = unsafe { self.kem.as_ref() };
-        kem.length_secret_key
+    #[test]
+    #[cfg(all(feature = "hkdf", feature = "kyber"))]
+    fn test_decapsulate_hkdf() -> Result<()> {
+        // HKDF-SHA256 of the Kyber768 KAT shared secret, computed independently
+        let expected = [
+            0x9e, 0xf3, 0xe7, 0xfa, 0x08, 0x6d, 0x51, 0x30, 0x23, 0x41, 0x7b, 0xb0, 0x72, 0x1d,
+            0x43, 0x25, 0x96, 0x64, 0x07, 0x0f, 0xf6, 0x34, 0x40, 0x18, 0x94, 0xc0, 0x33, 0x5c,
+            0x71, 0x3c, 0x7a, 0xf0, 0x86, 0xdf, 0xc3, 0xda, 0x0f, 0xf2, 0x31, 0x0b, 0x44, 0x89,
+            0xf6, 0x9d, 0x8f, 0xaa, 0xec, 0x9d,
+        ];
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber768)?;
+        let [_, sk, ct, _] = kyber768_kat();
+        let sk = kem.secret_key_from_bytes(&sk).unwrap();
+        let ct = kem.ciphertext_from_bytes(&ct).unwrap();
+        let mut out = [0u8; 48];
+        kem.decapsulate_hkdf(sk, ct, b"salt", b"record keys", &mut out)?;
+        assert_eq!(out, expected);
+        Ok(())
     }
 
-    /// Get the length of the ciphertext
-    pub fn length_ciphertext(&self) -> usize {
-        let kem = unsafe { self.kem.as_ref() };
-        kem.length_ciphertext
+    #[test]
+    #[cfg(all(feature = "kyber", feature = "metrics"))]
+    fn test_rejection_count() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (pk, sk) = kem.keypair()?;
+        let (ct, ss) = kem.encapsulate(&pk)?;
+        assert!(kem.decapsulate_and_confirm(&sk, &ct, ss.as_ref())?);
+        assert_eq!(kem.rejection_count(), 0);
+        assert!(!kem.decapsulate_and_confirm(&sk, &ct, &[0; 32])?);
+        assert!(!kem.decapsulate_and_confirm(&sk, &ct, &[1; 32])?);
+        assert_eq!(kem.rejection_count(), 2);
+        Ok(())
     }
 
-    /// Get the length of a shared secret
-    pub fn length_shared_secret(&self) -> usize {
-        let kem = unsafe { self.kem.as_ref() };
-        kem.length_shared_secret
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_warmup() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        kem.warmup()?;
+        kem.self_test()
     }
 
-    /// Get the length of a shared secret
-    pub fn length_ephemeral_secret(&self) -> usize {
-        let kem = unsafe { self.kem.as_ref() };
-        kem.length_ephemeral_secret
+    #[test]
+    fn test_operation_flags() {
+        let flags = OperationFlags::KEYPAIR | OperationFlags::DECAPSULATE;
+        assert!(flags.contains(OperationFlags::KEYPAIR));
+        assert!(!flags.contains(OperationFlags::ENCAPSULATE));
+        assert!(!flags.contains(OperationFlags::KEYPAIR | OperationFlags::ENCAPSULATE));
+        assert!(flags.contains(OperationFlags::empty()));
+        assert_eq!(flags.bits(), 0b101);
     }
 
-    /// Obtain a secret key objects from bytes
-    ///
-    /// Returns None if the secret key is not the correct length.
-    pub fn secret_key_from_bytes<'a>(&self, buf: &'a [u8]) -> Option<SecretKeyRef<'a>> {
-        if self.length_secret_key() != buf.len() {
-            None
-        } else {
-            Some(SecretKeyRef::new(buf))
+    #[test]
+    fn test_available_operations() -> Result<()> {
+        crate::init();
+        let basic =
+            OperationFlags::KEYPAIR | OperationFlags::ENCAPSULATE | OperationFlags::DECAPSULATE;
+        for alg in all().iter().copied().filter(|alg| alg.is_enabled()) {
+            let kem = Kem::new(alg)?;
+            let flags = kem.available_operations();
+            assert!(flags.contains(basic), "{:?}", alg);
+            assert_eq!(
+                flags.contains(OperationFlags::SPLIT_ENCAPSULATION),
+                kem.split_encapsulation_security() != SecurityNotion::Unsupported,
+                "{:?}",
+                alg
+            );
         }
+        Ok(())
     }
 
-    /// Obtain a public key from bytes
-    ///
-    /// Returns None if the public key is not the correct length.
-    pub fn public_key_from_bytes<'a>(&self, buf: &'a [u8]) -> Option<PublicKeyRef<'a>> {
-        if self.length_public_key() != buf.len() {
-            None
-        } else {
-            Some(PublicKeyRef::new(buf))
-        }
+    #[test]
+    #[cfg(feature = "sike")]
+    fn test_split_encapsulation_security() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::SikeP434Compressed)?;
+        kem.init()?;
+        assert_eq!(kem.split_encapsulation_security(), SecurityNotion::IndCpa);
+        let (pk, sk) = kem.keypair()?;
+        let (ct, es) = kem.encapsulate_ciphertext()?;
+        let (other_ct, other_es) = kem.encapsulate_ciphertext()?;
+        let ss = kem.decapsulate(&sk, &ct)?;
+        assert_eq!(kem.encapsulate_shared_secret(&ct, &es, &pk)?, ss);
+        // Nothing binds a ciphertext to its ephemeral secret
+        assert_ne!(kem.encapsulate_shared_secret(&ct, &other_es, &pk)?, ss);
+        assert_ne!(
+            kem.encapsulate_shared_secret(&other_ct, &es, &pk)?,
+            kem.decapsulate(&sk, &other_ct)?
+        );
+        Ok(())
     }
 
-    /// Obtain a ciphertext from bytes
-    ///
-    /// Returns None if the ciphertext is not the correct length.
-    pub fn ciphertext_from_bytes<'a>(&self, buf: &'a [u8]) -> Option<CiphertextRef<'a>> {
-        if self.length_ciphertext() != buf.len() {
-            None
-        } else {
-            Some(CiphertextRef::new(buf))
+    #[test]
+    fn test_algorithms_by_family() {
+        crate::init();
+        let families = algorithms_by_family();
+        let mut count = 0;
+        for (family, algorithms) in families.iter() {
+            assert!(!algorithms.is_empty());
+            let levels: Vec<u8> = algorithms
+                .iter()
+                .map(|&alg| Kem::new(alg).unwrap().claimed_nist_level())
+                .collect();
+            assert!(levels.windows(2).all(|w| w[0] >= w[1]));
+            assert!(algorithms.iter().all(|alg| alg.family() == *family));
+            count += algorithms.len();
         }
+        assert_eq!(count, all().iter().filter(|alg| alg.is_enabled()).count());
     }
 
-    /// Obtain a secret key from bytes
-    ///
-    /// Returns None if the shared secret is not the correct length.
-    pub fn shared_secret_from_bytes<'a>(&self, buf: &'a [u8]) -> Option<SharedSecretRef<'a>> {
-        if self.length_shared_secret() != buf.len() {
-            None
-        } else {
-            Some(SharedSecretRef::new(buf))
+    #[test]
+    fn test_grouped_shared_secret_lengths() {
+        let lengths = shared_secret_lengths(&[
+            Algorithm::Kyber768,
+            Algorithm::FrodoKem640Aes,
+            Algorithm::Hqc128,
+            Algorithm::Saber,
+        ]);
+        let groups: Vec<(usize, Vec<Algorithm>)> = lengths.into_iter().collect();
+        assert_eq!(
+            groups,
+            [
+                (16, alloc::vec![Algorithm::FrodoKem640Aes]),
+                (32, alloc::vec![Algorithm::Kyber768, Algorithm::Saber]),
+                (64, alloc::vec![Algorithm::Hqc128]),
+            ]
+        );
+        assert!(shared_secret_lengths(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_requires_openssl() {
+        assert!(Algorithm::Kyber768_90s.requires_openssl());
+        assert!(Algorithm::FrodoKem640Aes.requires_openssl());
+        assert!(!Algorithm::Kyber768.requires_openssl());
+        assert!(!Algorithm::FrodoKem640Shake.requires_openssl());
+
+        assert!(matches!(
+            disabled_error(Algorithm::Kyber768),
+            Error::AlgorithmDisabled
+        ));
+        assert_eq!(
+            matches!(
+                disabled_error(Algorithm::Kyber768_90s),
+                Error::MissingDependency("OpenSSL")
+            ),
+            cfg!(all(feature = "kyber", not(feature = "openssl")))
+        );
+    }
+
+    #[test]
+    fn test_ciphertext_expansion() {
+        assert_eq!(Algorithm::Kyber768.ciphertext_expansion(), 34.0);
+        assert_eq!(Algorithm::FrodoKem640Aes.ciphertext_expansion(), 607.5);
+        assert_eq!(Algorithm::ClassicMcEliece348864.ciphertext_expansion(), 4.0);
+
+        let ranked = ranked_by_expansion();
+        assert_eq!(ranked.len(), all().len());
+        assert!(ranked.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+        let position = |algorithm| ranked.iter().position(|&(alg, _)| alg == algorithm);
+        assert!(position(Algorithm::ClassicMcEliece348864) < position(Algorithm::Kyber768));
+        assert!(position(Algorithm::Kyber768) < position(Algorithm::FrodoKem640Aes));
+    }
+
+    /// liboqs identifiers deliberately not covered by [`Algorithm`]
+    const UNSUPPORTED_IDS: &[&str] = &[];
+
+    #[test]
+    fn test_algorithm_covers_liboqs() {
+        let count = unsafe { ffi::OQS_KEM_alg_count() };
+        assert!(count > 0);
+        for i in 0..count as usize {
+            // SAFETY: liboqs returns a null-terminated string for indices below the count
+            let id = unsafe { CStr::from_ptr(ffi::OQS_KEM_alg_identifier(i)) };
+            let id = id.to_str().unwrap();
+            assert!(
+                UNSUPPORTED_IDS.contains(&id) || all().iter().any(|alg| alg.name() == id),
+                "liboqs KEM {} has no Algorithm variant",
+                id
+            );
         }
     }
 
-    /// Obtain a secret key from bytes
-    ///
-    /// Returns None if the shared secret is not the correct length.
-    pub fn ephemeral_secret_from_bytes<'a>(&self, buf: &'a [u8]) -> Option<EphemeralSecretRef<'a>> {
-        if self.length_ephemeral_secret() != buf.len() {
-            None
-        } else {
-            Some(EphemeralSecretRef::new(buf))
+    #[test]
+    #[cfg(all(feature = "sike", feature = "std"))]
+    fn test_keypair_tested() -> Result<()> {
+        crate::init();
+        let kem = Kem::builder(Algorithm::SikeP434Compressed1CCA)
+            .auto_init(true)
+            .reuse_protection(true)
+            .build()?;
+        let (pk, sk) = kem.keypair_tested()?;
+        assert_eq!(pk.len(), kem.length_public_key());
+        // The consistency test does not use up the single-use key
+        let (ct, ss) = kem.encapsulate(&pk)?;
+        assert_eq!(kem.decapsulate(&sk, &ct)?, ss);
+        Ok(())
+    }
+
+    #[test]
+    fn test_as_cstr() {
+        for &algorithm in all() {
+            let cstr = algorithm.as_cstr();
+            let id = unsafe { CStr::from_ptr(algorithm.to_id()) };
+            assert_eq!(cstr.to_bytes_with_nul(), id.to_bytes_with_nul());
+            assert_eq!(cstr.as_ptr(), algorithm.to_id());
+            assert_eq!(cstr.to_bytes(), algorithm.name().as_bytes());
         }
     }
 
-    /// Initialize the KEM
-    pub fn init(&self) -> Result<()> {
-        let status = unsafe { ffi::OQS_KEM_init(self.kem.as_ptr()) };
-        status_to_result(status)?;
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_decapsulate_any() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (old_pk, old_sk) = kem.keypair()?;
+        let (new_pk, new_sk) = kem.keypair()?;
+        let sks = [SecretKeyRef::from(&old_sk), SecretKeyRef::from(&new_sk)];
 
+        let (ct, ss) = kem.encapsulate(&old_pk)?;
+        assert_eq!(kem.decapsulate_any(&sks, &ct, ss.as_ref())?, Some(0));
+        let (ct, ss) = kem.encapsulate(&new_pk)?;
+        assert_eq!(kem.decapsulate_any(&sks, &ct, ss.as_ref())?, Some(1));
+        assert_eq!(kem.decapsulate_any(&sks[..1], &ct, ss.as_ref())?, None);
+        assert_eq!(kem.decapsulate_any(&[], &ct, ss.as_ref())?, None);
+
+        let short = &old_sk.as_ref()[1..];
+        assert!(matches!(
+            kem.decapsulate_any(&[sks[0], SecretKeyRef::new(short)], &ct, ss.as_ref()),
+            Err(Error::Batch { index: 1, .. })
+        ));
         Ok(())
     }
 
-    /// Uninitialize the KEM
-    pub fn deinit(&self) -> Result<()> {
-        let status = unsafe { ffi::OQS_KEM_deinit() };
-        status_to_result(status)?;
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_verify_keypair() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber768)?;
+        let (pk, sk) = kem.keypair()?;
+        assert!(kem.verify_keypair(&pk, &sk)?);
 
+        let corrupted: Vec<u8> = sk.as_ref().iter().map(|byte| !byte).collect();
+        assert!(!kem.verify_keypair(&pk, kem.try_secret_key_ref(&corrupted)?)?);
+        let (_, other_sk) = kem.keypair()?;
+        assert!(!kem.verify_keypair(&pk, &other_sk)?);
+        assert!(matches!(
+            kem.verify_keypair(&pk, SecretKeyRef::new(&corrupted[1..])),
+            Err(Error::InvalidSecretKeyLength { .. })
+        ));
         Ok(())
     }
 
-    /// Generate a new keypair
-    pub fn keypair(&self) -> Result<(PublicKey, SecretKey)> {
-        let kem = unsafe { self.kem.as_ref() };
-        let func = kem.keypair.unwrap();
-        let mut pk = PublicKey {
-            bytes: Vec::with_capacity(kem.length_public_key),
-        };
-        let mut sk = SecretKey {
-            bytes: Vec::with_capacity(kem.length_secret_key),
-        };
-        let status = unsafe { func(pk.bytes.as_mut_ptr(), sk.bytes.as_mut_ptr()) };
-        status_to_result(status)?;
-        // update the lengths of the vecs
-        // this is safe to do, as we have initialised them now.
-        unsafe {
-            pk.bytes.set_len(kem.length_public_key);
-            sk.bytes.set_len(kem.length_secret_key);
+    #[test]
+    #[cfg(all(feature = "classic_mceliece", feature = "numa"))]
+    fn test_new_on_node() -> Result<()> {
+        crate::init();
+        let kem = Kem::new_on_node(Algorithm::ClassicMcEliece348864, 0)?;
+        let (pk, sk) = kem.keypair()?;
+        let (ct, ss) = kem.encapsulate(&pk)?;
+        assert_eq!(kem.decapsulate(&sk, &ct)?, ss);
+        if crate::numa::has_node(0) {
+            assert!(matches!(
+                Kem::new_on_node(Algorithm::ClassicMcEliece348864, u32::MAX),
+                Err(Error::OperationNotSupported)
+            ));
         }
-        Ok((pk, sk))
+        Ok(())
     }
 
-    /// Generate a new keypair
-    pub fn keypair_async(&self) -> Result<(PublicKey, SecretKey)> {
-        let kem = unsafe { self.kem.as_ref() };
-        let mut pk = PublicKey {
-            bytes: Vec::with_capacity(kem.length_public_key),
-        };
-        let mut sk = SecretKey {
-            bytes: Vec::with_capacity(kem.length_secret_key),
-        };
-        let status = unsafe { ffi::OQS_KEM_async_keypair(self.kem.as_ptr(), pk.bytes.as_mut_ptr(), sk.bytes.as_mut_ptr()) };
-        status_to_result(status)?;
-        // update the lengths of the vecs
-        // this is safe to do, as we have initialised them now.
-        unsafe {
-            pk.bytes.set_len(kem.length_public_key);
-            sk.bytes.set_len(kem.length_secret_key);
-        }
-        Ok((pk, sk))
+    #[test]
+    #[cfg(all(feature = "kyber", feature = "std"))]
+    fn test_keypair_cancellable() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (pk, sk) = kem.keypair_cancellable(&AtomicBool::new(false))?;
+        let (ct, ss) = kem.encapsulate(&pk)?;
+        assert_eq!(kem.decapsulate(&sk, &ct)?, ss);
+        assert!(matches!(
+            kem.keypair_cancellable(&AtomicBool::new(true)),
+            Err(Error::Cancelled)
+        ));
+        Ok(())
     }
 
-    /// Encapsulate to the provided public key
-    pub fn encapsulate<'a, P: Into<PublicKeyRef<'a>>>(
-        &self,
-        pk: P,
-    ) -> Result<(Ciphertext, SharedSecret)> {
-        let pk = pk.into();
-        if pk.bytes.len() != self.length_public_key() {
-            return Err(Error::InvalidLength);
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_decapsulate_many() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (pk, sk) = kem.keypair()?;
+        let encapsulations = (0..4)
+            .map(|_| kem.encapsulate(&pk))
+            .collect::<Result<Vec<_>>>()?;
+        let cts: Vec<CiphertextRef> = encapsulations.iter().map(|(ct, _)| ct.into()).collect();
+        let secrets = kem.decapsulate_many(&sk, &cts)?;
+        assert_eq!(secrets.len(), 4);
+        for (ss, (_, expected)) in secrets.iter().zip(&encapsulations) {
+            assert_eq!(ss, expected);
         }
-        let kem = unsafe { self.kem.as_ref() };
-        let func = kem.encaps.unwrap();
-        let mut ct = Ciphertext {
-            bytes: Vec::with_capacity(kem.length_ciphertext),
-        };
-        let mut ss = SharedSecret {
-            bytes: Vec::with_capacity(kem.length_shared_secret),
-        };
-        // call encapsulate
-        let status = unsafe {
-            func(
-                ct.bytes.as_mut_ptr(),
-                ss.bytes.as_mut_ptr(),
-                pk.bytes.as_ptr(),
-            )
-        };
-        status_to_result(status)?;
-        // update the lengths of the vecs
-        // this is safe to do, as we have initialised them now.
-        unsafe {
-            ct.bytes.set_len(kem.length_ciphertext);
-            ss.bytes.set_len(kem.length_shared_secret);
+        assert!(kem.decapsulate_many(&sk, &[])?.is_empty());
+
+        let short = CiphertextRef::new(&cts[1].bytes[1..]);
+        let bad = [cts[0], short, cts[2]];
+        match kem.decapsulate_many(&sk, &bad) {
+            Err(Error::Batch { index: 1, error }) => {
+                assert!(matches!(*error, Error::InvalidCiphertextLength { .. }))
+            }
+            other => panic!("expected a batch error, got {:?}", other),
         }
-        Ok((ct, ss))
+        let short_sk = SecretKeyRef::new(&sk.bytes[1..]);
+        assert!(matches!(
+            kem.decapsulate_many(short_sk, &cts),
+            Err(Error::InvalidSecretKeyLength { .. })
+        ));
+        Ok(())
     }
 
-    /// Async encapsulate to the provided public key
-    pub fn async_encapsulate<'a, P: Into<PublicKeyRef<'a>>>(
-        &self,
-        pk: P,
-    ) -> Result<(Ciphertext, SharedSecret)> {
-        let pk = pk.into();
-        if pk.bytes.len() != self.length_public_key() {
-            return Err(Error::InvalidLength);
+    #[test]
+    fn test_algorithms_under() {
+        crate::init();
+        let enabled = all().iter().filter(|alg| alg.is_enabled()).count();
+        assert_eq!(algorithms_under(usize::MAX, usize::MAX).len(), enabled);
+        assert!(algorithms_under(0, usize::MAX).is_empty());
+
+        let (max_ct, max_pk) = (1100, 1200);
+        let algorithms = algorithms_under(max_ct, max_pk);
+        let sizes: Vec<(usize, usize)> = algorithms
+            .iter()
+            .map(|&alg| {
+                let kem = Kem::new(alg).unwrap();
+                (kem.length_ciphertext(), kem.length_public_key())
+            })
+            .collect();
+        assert!(sizes.iter().all(|&(ct, pk)| ct <= max_ct && pk <= max_pk));
+        assert!(sizes.windows(2).all(|w| w[0].0 <= w[1].0));
+        #[cfg(feature = "kyber")]
+        {
+            assert!(algorithms.contains(&Algorithm::Kyber768));
+            assert!(!algorithms.contains(&Algorithm::Kyber1024));
         }
-        let kem = unsafe { self.kem.as_ref() };
-        let func = kem.async_encaps.unwrap();
-        let mut ct = Ciphertext {
-            bytes: Vec::with_capacity(kem.length_ciphertext),
-        };
-        let mut ss = SharedSecret {
-            bytes: Vec::with_capacity(kem.length_shared_secret),
-        };
-        // call encapsulate
-        let status = unsafe {
-            func(
-                ct.bytes.as_mut_ptr(),
-                ss.bytes.as_mut_ptr(),
-                pk.bytes.as_ptr(),
-            )
-        };
-        status_to_result(status)?;
-        // update the lengths of the vecs
-        // this is safe to do, as we have initialised them now.
-        unsafe {
-            ct.bytes.set_len(kem.length_ciphertext);
-            ss.bytes.set_len(kem.length_shared_secret);
+    }
+
+    #[test]
+    fn test_estimated_security_bits() {
+        for alg in all() {
+            if let Some(bits) = alg.estimated_security_bits() {
+                assert!(
+                    bits.quantum > 0 && bits.quantum <= bits.classical,
+                    "{:?}",
+                    alg
+                );
+            }
         }
-        Ok((ct, ss))
+        let kyber768 = Algorithm::Kyber768.estimated_security_bits().unwrap();
+        assert_eq!(kyber768.classical, 183);
+        assert_eq!(kyber768.quantum, 166);
+        assert_eq!(
+            Some(kyber768),
+            Algorithm::Kyber768_90s.estimated_security_bits()
+        );
+        assert_eq!(Algorithm::FrodoKem640Shake.estimated_security_bits(), None);
+        assert_eq!(Algorithm::SikeP434.estimated_security_bits(), None);
+        assert_eq!(Algorithm::NtruHrss1373.estimated_security_bits(), None);
     }
 
+    #[test]
+    fn test_iter_enabled_kems() -> Result<()> {
+        crate::init();
+        assert_eq!(
+            iter_enabled_kems().count(),
+            all().iter().filter(|alg| alg.is_enabled()).count()
+        );
+        for kem in iter_enabled_kems() {
+            let kem = kem?;
+            if kem.algorithm().is_nike() {
+                continue;
+            }
+            let (pk, sk) = kem.keypair()?;
+            let (ct, ss) = kem.encapsulate(&pk)?;
+            assert_eq!(kem.decapsulate(&sk, &ct)?, ss);
+        }
+        Ok(())
+    }
 
-    /// Encapsulate ciphertext
-    pub fn encapsulate_ciphertext(&self) -> Result<(Ciphertext, EphemeralSecret)> {
-        let kem = unsafe { self.kem.as_ref() };
-        let mut ct = Ciphertext {
-            bytes: Vec::with_capacity(kem.length_ciphertext),
-        };
-        let mut es = EphemeralSecret {
-            bytes: Vec::with_capacity(kem.length_ephemeral_secret),
-        };
-        // call encapsulate_ciphertext
-        let status = unsafe {
-            ffi::OQS_KEM_encaps_ciphertext(
-                self.kem.as_ptr(),
-                ct.bytes.as_mut_ptr(),
-                es.bytes.as_mut_ptr(),
-            )
-        };
-        status_to_result(status)?;
-        // update the lengths of the vecs
-        // this is safe to do, as we have initialised them now.
-        unsafe {
-            ct.bytes.set_len(kem.length_ciphertext);
-            es.bytes.set_len(kem.length_ephemeral_secret);
+    #[test]
+    fn test_safe_enabled() {
+        crate::init();
+        let algorithms = safe_enabled();
+        assert!(algorithms
+            .iter()
+            .all(|alg| alg.is_enabled() && !alg.is_cryptographically_broken()));
+        let keys: Vec<(u8, Family)> = algorithms
+            .iter()
+            .map(|&alg| (Kem::new(alg).unwrap().claimed_nist_level(), alg.family()))
+            .collect();
+        assert!(keys.windows(2).all(|w| w[0] <= w[1]));
+        #[cfg(feature = "kyber")]
+        assert!(algorithms.contains(&Algorithm::Kyber512));
+        #[cfg(feature = "sike")]
+        assert!(!algorithms.contains(&Algorithm::SikeP434));
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_diagnose_ciphertext() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (pk, sk) = kem.keypair()?;
+        let (ct, _) = kem.encapsulate(&pk)?;
+        assert_eq!(
+            kem.diagnose_ciphertext(ct.as_ref()),
+            CiphertextDiagnosis::Exact
+        );
+        let short = &ct.as_ref()[..ct.len() - 3];
+        assert_eq!(
+            kem.diagnose_ciphertext(short),
+            CiphertextDiagnosis::TooShort(3)
+        );
+        assert!(matches!(
+            kem.decapsulate(&sk, CiphertextRef::new(short)),
+            Err(Error::InvalidCiphertextLength {
+                expected: 768,
+                got: 765
+            })
+        ));
+        let mut long = ct.into_vec();
+        long.push(0);
+        assert_eq!(
+            kem.diagnose_ciphertext(&long),
+            CiphertextDiagnosis::TooLong(1)
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(feature = "kyber", feature = "std"))]
+    fn test_destroy() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (encaps_ss, decaps_ss) = kem.roundtrip()?;
+        assert_eq!(encaps_ss, decaps_ss);
+        drop(kem);
+        crate::destroy()?;
+        crate::destroy()
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_keypair_sk_into() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let mut sk = [0u8; 1632];
+        let pk = kem.keypair_sk_into(&mut sk)?;
+        assert!(sk.iter().any(|&byte| byte != 0));
+        let (ct, ss) = kem.encapsulate(&pk)?;
+        assert_eq!(kem.decapsulate(kem.try_secret_key_ref(&sk)?, &ct)?, ss);
+        assert!(matches!(
+            kem.keypair_sk_into(&mut sk[1..]),
+            Err(Error::InvalidSecretKeyLength {
+                expected: 1632,
+                got: 1631
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_public_key_from_padded() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (pk, sk) = kem.keypair()?;
+        let mut column = pk.as_ref().to_vec();
+        assert_eq!(
+            kem.public_key_from_padded(&column)?,
+            PublicKeyRef::from(&pk)
+        );
+        column.resize(1024, 0);
+        let padded = kem.public_key_from_padded(&column)?;
+        assert_eq!(padded.to_owned(), pk);
+        let (ct, ss) = kem.encapsulate(padded)?;
+        assert_eq!(kem.decapsulate(&sk, &ct)?, ss);
+
+        column[1000] = 1;
+        assert!(matches!(
+            kem.public_key_from_padded(&column),
+            Err(Error::InvalidFormat)
+        ));
+        assert!(matches!(
+            kem.public_key_from_padded(&column[..799]),
+            Err(Error::InvalidLength {
+                expected: 800,
+                got: 799
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_str() {
+        for &algorithm in all() {
+            assert_eq!(algorithm.name().parse::<Algorithm>().unwrap(), algorithm);
         }
-        Ok((ct, es))
+        assert!(matches!(
+            "KYBER512".parse::<Algorithm>(),
+            Err(Error::InvalidFormat)
+        ));
+        assert!("".parse::<Algorithm>().is_err());
     }
 
-    pub fn encapsulate_shared_secret<'a, 
-        P: Into<PublicKeyRef<'a>>,
-        C: Into<CiphertextRef<'a>>,
-        E: Into<EphemeralSecretRef<'a>>,
-    >(
-        &self,
-        ct: C,
-        es: E,
-        pk: P,
-    ) -> Result<SharedSecret> {
-        let pk = pk.into();
-        if pk.bytes.len() != self.length_public_key() {
-            return Err(Error::InvalidLength);
+    #[test]
+    fn test_parse_relaxed() {
+        for &algorithm in all() {
+            assert_eq!(Algorithm::parse_relaxed(algorithm.name()), Some(algorithm));
+            assert_eq!(
+                Algorithm::parse_relaxed(algorithm.variant_name()),
+                Some(algorithm)
+            );
         }
-        let kem = unsafe { self.kem.as_ref() };
+        let table = [
+            ("kyber512", Some(Algorithm::Kyber512)),
+            ("KYBER-512", Some(Algorithm::Kyber512)),
+            ("ML-KEM-512", Some(Algorithm::Kyber512)),
+            ("ml_kem 768", Some(Algorithm::Kyber768)),
+            ("mlkem1024", Some(Algorithm::Kyber1024)),
+            ("Kyber512_90s", Some(Algorithm::Kyber512_90s)),
+            ("kyber 768 90s", Some(Algorithm::Kyber768_90s)),
+            ("sike p434 compressed", Some(Algorithm::SikeP434Compressed)),
+            (
+                "SIKE_P434_1CCA_COMPRESSED",
+                Some(Algorithm::SikeP434Compressed1CCA),
+            ),
+            (
+                "classic mceliece 348864f",
+                Some(Algorithm::ClassicMcEliece348864f),
+            ),
+            ("frodokem640shake", Some(Algorithm::FrodoKem640Shake)),
+            ("ml-kem", None),
+            ("kyber", None),
+            ("", None),
+            ("kyber512!", None),
+        ];
+        for &(input, expected) in table.iter() {
+            assert_eq!(Algorithm::parse_relaxed(input), expected, "{:?}", input);
+        }
+    }
 
-        let ct = ct.into();
-        if ct.bytes.len() != kem.length_ciphertext {
-            return Err(Error::InvalidLength);
+    #[test]
+    fn test_nist_selected() {
+        crate::init();
+        let selected: Vec<Algorithm> = all()
+            .iter()
+            .copied()
+            .filter(Algorithm::is_nist_selected)
+            .collect();
+        assert!(recommended().iter().all(|alg| selected.contains(alg)));
+        assert!(selected
+            .iter()
+            .all(|alg| matches!(alg.family(), Family::Kyber | Family::Hqc)));
+        let enabled = nist_selected_enabled();
+        let safe = safe_enabled();
+        assert!(enabled
+            .iter()
+            .all(|alg| alg.is_nist_selected() && safe.contains(alg)));
+        #[cfg(feature = "kyber")]
+        {
+            assert!(enabled.contains(&Algorithm::Kyber768));
+            assert!(!enabled.contains(&Algorithm::Kyber768_90s));
         }
-        let es = es.into();
-        if es.bytes.len() != kem.length_ephemeral_secret {
-            return Err(Error::InvalidLength);
+        #[cfg(feature = "hqc")]
+        assert!(enabled.contains(&Algorithm::Hqc128));
+    }
+
+    #[test]
+    #[cfg(all(feature = "sike", feature = "std"))]
+    fn test_concurrent_init() -> Result<()> {
+        crate::init();
+        let algorithm = Algorithm::SikeP434Compressed;
+        let threads: Vec<_> = (0..16)
+            .map(|i| {
+                std::thread::spawn(move || -> Result<()> {
+                    for _ in 0..4 {
+                        let kem = Kem::new(algorithm)?;
+                        kem.init()?;
+                        kem.init()?;
+                        let (pk, sk) = kem.keypair()?;
+                        let (ct, ss1) = kem.encapsulate(&pk)?;
+                        assert_eq!(ss1, kem.decapsulate(&sk, &ct)?);
+                        // Half of them deinit explicitly, the other half through Drop
+                        if i % 2 == 0 {
+                            kem.deinit()?;
+                            kem.deinit()?;
+                        }
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap()?;
         }
+        assert!(!init_refs().contains_key(&algorithm));
+        Ok(())
+    }
 
-        let func = kem.encaps_shared_secret.unwrap();
-        let mut ss = SharedSecret {
-            bytes: Vec::with_capacity(kem.length_shared_secret),
-        };
-        // call encapsulate
-        let status = unsafe {
-            func(
-                ss.bytes.as_mut_ptr(),
-                ct.bytes.as_ptr(),
-                es.bytes.as_ptr(),
-                pk.bytes.as_ptr(),
-            )
-        };
-        status_to_result(status)?;
-        // update the lengths of the vecs
-        // this is safe to do, as we have initialised them now.
-        unsafe {
-            ss.bytes.set_len(kem.length_shared_secret);
+    #[test]
+    fn test_size_class() {
+        assert_eq!(Algorithm::Kyber768.size_class(), SizeClass::Compact);
+        assert_eq!(Algorithm::FrodoKem976Aes.size_class(), SizeClass::Large);
+        assert_eq!(
+            Algorithm::ClassicMcEliece8192128.size_class(),
+            SizeClass::HugeKeys
+        );
+        crate::init();
+        for &alg in all() {
+            if let Ok(kem) = Kem::new(alg) {
+                let class =
+                    SizeClass::from_lengths(kem.length_public_key(), kem.length_ciphertext());
+                assert_eq!(alg.size_class(), class, "{:?} is misclassified", alg);
+            }
         }
-        Ok(ss)
     }
 
-    /// Decapsulate the provided ciphertext
-    pub fn decapsulate<'a, 'b, S: Into<SecretKeyRef<'a>>, C: Into<CiphertextRef<'b>>>(
-        &self,
-        sk: S,
-        ct: C,
-    ) -> Result<SharedSecret> {
-        let kem = unsafe { self.kem.as_ref() };
-        let sk = sk.into();
-        let ct = ct.into();
-        if sk.bytes.len() != self.length_secret_key() || ct.bytes.len() != self.length_ciphertext()
-        {
-            return Err(Error::InvalidLength);
+    #[test]
+    fn test_tagged_public_key() -> Result<()> {
+        use core::convert::TryFrom;
+        crate::init();
+        for &alg in all().iter().filter(|alg| alg.is_enabled()) {
+            let kem = Kem::new(alg)?;
+            let (pk, _) = kem.keypair()?;
+            let tagged = TaggedPublicKey::try_from((alg, pk.clone().into_vec()))?;
+            assert_eq!(tagged.algorithm(), alg);
+            assert_eq!(tagged.public_key(), &pk);
+
+            let mut bytes = pk.into_vec();
+            bytes.pop();
+            assert!(matches!(
+                TaggedPublicKey::try_from((alg, bytes)),
+                Err(Error::InvalidLength { .. })
+            ));
         }
-        let mut ss = SharedSecret {
-            bytes: Vec::with_capacity(kem.length_shared_secret),
-        };
-        let func = kem.decaps.unwrap();
-        // Call decapsulate
-        let status = unsafe { func(ss.bytes.as_mut_ptr(), ct.bytes.as_ptr(), sk.bytes.as_ptr()) };
-        status_to_result(status)?;
-        // update the lengths of the vecs
-        // this is safe to do, as we have initialised them now.
-        unsafe { ss.bytes.set_len(kem.length_shared_secret) };
-        Ok(ss)
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_accepts() -> Result<()> {
+        use core::convert::TryFrom;
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber768)?;
+        let (pk, sk) = kem.keypair()?;
+        let tagged = TaggedPublicKey::try_from((Algorithm::Kyber768, pk.into_vec()))?;
+        assert!(kem.accepts(&tagged));
+        let (ct, ss) = kem.encapsulate_tagged(&tagged)?;
+        assert_eq!(kem.decapsulate_tagged(&tagged, &sk, &ct)?, ss);
+        let mut buf = alloc::vec![0; kem.length_shared_secret()];
+        kem.decapsulate_tagged_into(&tagged, &sk, &ct, &mut buf)?;
+        assert_eq!(buf, ss.as_ref());
+
+        // Same key length, different algorithm
+        let other = Kem::new(Algorithm::Kyber768_90s)?;
+        assert_eq!(other.length_public_key(), kem.length_public_key());
+        assert!(!other.accepts(&tagged));
+        assert!(matches!(
+            other.encapsulate_tagged(&tagged),
+            Err(Error::AlgorithmMismatch)
+        ));
+        assert!(matches!(
+            other.decapsulate_tagged(&tagged, &sk, &ct),
+            Err(Error::AlgorithmMismatch)
+        ));
+        assert!(matches!(
+            other.decapsulate_tagged_into(&tagged, &sk, &ct, &mut buf),
+            Err(Error::AlgorithmMismatch)
+        ));
+        Ok(())
     }
 }
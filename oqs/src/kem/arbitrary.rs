@@ -0,0 +1,130 @@
+//! `arbitrary::Arbitrary` support for property-based testing
+//!
+//! [`Algorithm`] picks one of the enabled algorithms. As the buffer types do not know
+//! their algorithm, [`TaggedPublicKey`] and [`KeyPair`] pick an enabled algorithm
+//! first, while the `Kem::arbitrary_*` functions generate buffers for a given `Kem`.
+//!
+//! The generated buffers have the right length, but their bytes are taken from the
+//! fuzzer input: they are not valid keys or ciphertexts, and operations on them
+//! produce meaningless results. Secret keys are only generated as part of a
+//! [`KeyPair`], see [`Kem::arbitrary_keypair`], which scrubs them when dropped.
+//!
+//! This module requires the `arbitrary` feature.
+use ::arbitrary::{Arbitrary, Unstructured};
+
+use super::*;
+
+impl<'a> Arbitrary<'a> for Algorithm {
+    fn arbitrary(u: &mut Unstructured<'a>) -> ::arbitrary::Result<Self> {
+        let enabled: Vec<Algorithm> = enabled_algorithms().collect();
+        u.choose(&enabled).copied()
+    }
+}
+
+/// Construct the `Kem` for a generated algorithm
+fn arbitrary_kem(u: &mut Unstructured) -> ::arbitrary::Result<Kem> {
+    Kem::new(Algorithm::arbitrary(u)?).map_err(|_| ::arbitrary::Error::IncorrectFormat)
+}
+
+impl<'a> Arbitrary<'a> for TaggedPublicKey {
+    fn arbitrary(u: &mut Unstructured<'a>) -> ::arbitrary::Result<Self> {
+        let kem = arbitrary_kem(u)?;
+        Ok(TaggedPublicKey {
+            algorithm: kem.algorithm(),
+            public_key: kem.arbitrary_public_key(u)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for KeyPair {
+    fn arbitrary(u: &mut Unstructured<'a>) -> ::arbitrary::Result<Self> {
+        arbitrary_kem(u)?.arbitrary_keypair(u)
+    }
+}
+
+macro_rules! arbitrary_buffers {
+    ($($function: ident, $name: ident, $length: ident);* $(;)?) => {
+        impl Kem {
+            $(
+                #[doc = concat!("Generate a [`", stringify!($name), "`] of the right length for this `Kem`")]
+                ///
+                /// For property-based testing: only the length is valid, the bytes are
+                /// taken from `u` and do not form a valid key or ciphertext.
+                /// Requires the `arbitrary` feature.
+                pub fn $function(&self, u: &mut Unstructured) -> ::arbitrary::Result<$name> {
+                    Ok($name {
+                        bytes: u.bytes(self.$length())?.to_vec(),
+                    })
+                }
+            )*
+        }
+    };
+}
+
+arbitrary_buffers! {
+    arbitrary_public_key, PublicKey, length_public_key;
+    arbitrary_ciphertext, Ciphertext, length_ciphertext;
+    arbitrary_shared_secret, SharedSecret, length_shared_secret;
+}
+
+impl Kem {
+    /// Generate a [`KeyPair`] of the right lengths for this `Kem`
+    ///
+    /// For property-based testing, like [`Kem::arbitrary_public_key`]. The secret
+    /// key is scrubbed when the `KeyPair` is dropped.
+    /// Requires the `arbitrary` feature.
+    pub fn arbitrary_keypair(&self, u: &mut Unstructured) -> ::arbitrary::Result<KeyPair> {
+        Ok(KeyPair {
+            public: self.arbitrary_public_key(u)?,
+            secret: SecretKey {
+                bytes: u.bytes(self.length_secret_key())?.to_vec(),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_arbitrary_lengths() {
+        const ROUNDS: usize = 8;
+        crate::init();
+        // Enough input for the largest enabled algorithm in every round, plus
+        // a few bytes per round to choose the algorithms
+        let round = enabled_algorithms()
+            .map(|alg| {
+                let sizes = alg.sizes();
+                2 * sizes.public_key + sizes.secret_key + sizes.ciphertext + sizes.shared_secret
+            })
+            .max()
+            .unwrap();
+        let data: Vec<u8> = (0..=255u8).cycle().take(ROUNDS * (round + 64)).collect();
+        let mut u = Unstructured::new(&data);
+        for _ in 0..ROUNDS {
+            let tagged = TaggedPublicKey::arbitrary(&mut u).unwrap();
+            assert!(tagged.algorithm().is_enabled());
+            let kem = Kem::new(tagged.algorithm()).unwrap();
+            assert!(kem.accepts(&tagged));
+            assert_eq!(
+                kem.arbitrary_ciphertext(&mut u).unwrap().len(),
+                kem.length_ciphertext()
+            );
+            assert_eq!(
+                kem.arbitrary_shared_secret(&mut u).unwrap().len(),
+                kem.length_shared_secret()
+            );
+            assert!(KeyPair::arbitrary(&mut u).is_ok());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_not_enough_data() {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512).unwrap();
+        let mut u = Unstructured::new(&[1, 2, 3]);
+        assert!(kem.arbitrary_keypair(&mut u).is_err());
+    }
+}
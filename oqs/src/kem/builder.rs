@@ -0,0 +1,182 @@
+//! Builder for [`Kem`]s with non-default options
+
+#[cfg(feature = "std")]
+use alloc::boxed::Box;
+
+use super::{Algorithm, Kem};
+use crate::*;
+
+/// Configures and constructs a [`Kem`]
+///
+/// Obtained through [`Kem::builder`]. Without any options set,
+/// [`KemBuilder::build`] behaves like [`Kem::new`].
+///
+/// ```
+/// # if !cfg!(feature = "sike") { return; }
+/// use oqs::kem::{Algorithm, Kem};
+/// oqs::init();
+/// let kem = Kem::builder(Algorithm::SikeP434Compressed)
+///     .auto_init(true)
+///     .build()
+///     .unwrap();
+/// let (pk, sk) = kem.keypair().unwrap();
+/// ```
+pub struct KemBuilder {
+    algorithm: Algorithm,
+    auto_init: bool,
+    #[cfg(feature = "std")]
+    rng: Option<Box<dyn crate::rand::Rng>>,
+    #[cfg(feature = "std")]
+    reuse_protection: bool,
+}
+
+impl KemBuilder {
+    /// Start configuring a `Kem` for `algorithm`
+    pub fn new(algorithm: Algorithm) -> Self {
+        KemBuilder {
+            algorithm,
+            auto_init: false,
+            #[cfg(feature = "std")]
+            rng: None,
+            #[cfg(feature = "std")]
+            reuse_protection: false,
+        }
+    }
+
+    /// Call [`Kem::init`] when building, so the `Kem` is ready for use
+    ///
+    /// Off by default. Algorithms that need no initialization are not affected.
+    pub fn auto_init(mut self, auto_init: bool) -> Self {
+        self.auto_init = auto_init;
+        self
+    }
+
+    /// Install `rng` through [`rand::set_rng`](crate::rand::set_rng) when building
+    ///
+    /// liboqs has a single generator for the whole process, so this also affects
+    /// all other `Kem`s and `Sig`s, on every thread. It stays installed after the
+    /// built `Kem` is dropped, until it is replaced with
+    /// [`rand::set_rng`](crate::rand::set_rng) or
+    /// [`rand::use_system_rng`](crate::rand::use_system_rng). To use a generator
+    /// for a limited time only, call [`rand::with_rng`](crate::rand::with_rng)
+    /// instead. By default the current generator is kept.
+    #[cfg(feature = "std")]
+    pub fn rng(mut self, rng: impl crate::rand::Rng + 'static) -> Self {
+        self.rng = Some(Box::new(rng));
+        self
+    }
+
+    /// Refuse to decapsulate twice with the same secret key
+    ///
+    /// Meant for ephemeral keys and for algorithms that are only secure for a single
+    /// use of a key pair, such as the 1CCA variants. Once a secret key has been used
+    /// in a successful decapsulation by any method of the built `Kem`, using it
+    /// again returns [`Error::KeyReused`]. A decapsulation that fails, for example
+    /// on a wrong length, does not use up the key. The `Kem` keeps a 64-bit
+    /// fingerprint of every secret key it has seen, so memory use grows with the
+    /// number of keys. Off by default.
+    #[cfg(feature = "std")]
+    pub fn reuse_protection(mut self, reuse_protection: bool) -> Self {
+        self.reuse_protection = reuse_protection;
+        self
+    }
+
+    /// Construct the `Kem`
+    pub fn build(self) -> Result<Kem> {
+        #[cfg_attr(not(feature = "std"), allow(unused_mut))]
+        let mut kem = Kem::new(self.algorithm)?;
+        #[cfg(feature = "std")]
+        {
+            if self.reuse_protection {
+                kem.used_secret_keys = Some(Default::default());
+            }
+            if let Some(rng) = self.rng {
+                crate::rand::set_boxed_rng(rng);
+            }
+        }
+        if self.auto_init {
+            kem.init()?;
+        }
+        Ok(kem)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_default_build() -> Result<()> {
+        crate::init();
+        let kem = Kem::builder(Algorithm::Kyber512).build()?;
+        assert_eq!(kem.algorithm(), Algorithm::Kyber512);
+        let (pk, sk) = kem.keypair()?;
+        let (ct, _) = kem.encapsulate(&pk)?;
+        // Without reuse protection, a key can be used repeatedly
+        kem.decapsulate(&sk, &ct)?;
+        kem.decapsulate(&sk, &ct)?;
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(feature = "sike", feature = "std"))]
+    fn test_auto_init_reuse_protection() -> Result<()> {
+        crate::init();
+        let kem = Kem::builder(Algorithm::SikeP434Compressed1CCA)
+            .auto_init(true)
+            .reuse_protection(true)
+            .build()?;
+        let (pk, sk) = kem.keypair()?;
+        let (ct, ss) = kem.encapsulate(&pk)?;
+        assert_eq!(kem.decapsulate(&sk, &ct)?, ss);
+        assert!(matches!(kem.decapsulate(&sk, &ct), Err(Error::KeyReused)));
+        assert!(matches!(
+            kem.decapsulate_then(&sk, &ct, |_| ()),
+            Err(Error::KeyReused)
+        ));
+
+        let (pk, sk) = kem.keypair()?;
+        let (ct, ss) = kem.encapsulate(&pk)?;
+        let mut buf = alloc::vec![0; kem.length_shared_secret()];
+        assert!(matches!(
+            kem.decapsulate_into(&sk, &ct, &mut buf[1..]),
+            Err(Error::InvalidLength { .. })
+        ));
+        // The failed call did not use up the key
+        kem.decapsulate_into(&sk, &ct, &mut buf)?;
+        assert_eq!(buf, ss.as_ref());
+        assert!(matches!(
+            kem.decapsulate_into(&sk, &ct, &mut buf),
+            Err(Error::KeyReused)
+        ));
+
+        let (pk, sk) = kem.keypair()?;
+        let (ct, ss) = kem.encapsulate(&pk)?;
+        assert!(kem.decapsulate_and_confirm(&sk, &ct, ss.as_ref())?);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(feature = "kyber", feature = "std"))]
+    fn test_rng() -> Result<()> {
+        struct ZeroRng;
+        impl crate::rand::Rng for ZeroRng {
+            fn fill_bytes(&mut self, dest: &mut [u8]) {
+                dest.iter_mut().for_each(|b| *b = 0);
+            }
+        }
+
+        let _lock = crate::rand::test::RNG_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        crate::init();
+        let kem = Kem::builder(Algorithm::Kyber512).rng(ZeroRng).build()?;
+        let (pk1, _) = kem.keypair()?;
+        let (pk2, _) = kem.keypair()?;
+        crate::rand::use_system_rng()?;
+        // A constant generator makes key generation deterministic
+        assert_eq!(pk1, pk2);
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+//! Side-by-side measurements of several algorithms
+
+use alloc::vec::Vec;
+use std::time::{Duration, Instant};
+
+use super::{Algorithm, Kem, KemSizes};
+use crate::*;
+
+/// Sizes and timings of one algorithm, see [`compare`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ComparisonRow {
+    /// The measured algorithm
+    pub algorithm: Algorithm,
+    /// The sizes of its keys, ciphertexts and shared secrets
+    pub sizes: KemSizes,
+    /// Mean time to generate a key pair
+    pub keypair: Duration,
+    /// Mean time to encapsulate
+    pub encapsulate: Duration,
+    /// Mean time to decapsulate
+    pub decapsulate: Duration,
+}
+
+/// Measure each of `algorithms` over `samples` fresh key pairs
+///
+/// For every sample, this generates a key pair, encapsulates to it and
+/// decapsulates the result, and checks that both sides agree on the shared
+/// secret. The rows report the mean time per operation, in the order of
+/// `algorithms`. Algorithms that are disabled in the linked liboqs are skipped;
+/// those that need [`Kem::init`] are initialized first, which is not timed.
+/// At least one sample is taken.
+///
+/// This is meant for comparing algorithms at a glance, not as a rigorous
+/// benchmark: a few samples are noisy, and the first ones include warm-up.
+/// Returns the first error any operation reports.
+pub fn compare(algorithms: &[Algorithm], samples: u32) -> Result<Vec<ComparisonRow>> {
+    let samples = samples.max(1);
+    let mut rows = Vec::with_capacity(algorithms.len());
+    for &algorithm in algorithms {
+        let kem = match Kem::new(algorithm) {
+            Ok(kem) => kem,
+            Err(Error::AlgorithmDisabled | Error::MissingDependency(_)) => continue,
+            Err(error) => return Err(error),
+        };
+        if algorithm.requires_init() {
+            kem.init()?;
+        }
+        let mut timings = [Duration::default(); 3];
+        for _ in 0..samples {
+            let start = Instant::now();
+            let (pk, sk) = kem.keypair()?;
+            timings[0] += start.elapsed();
+
+            let start = Instant::now();
+            let (ct, encaps_ss) = kem.encapsulate(&pk)?;
+            timings[1] += start.elapsed();
+
+            let start = Instant::now();
+            let decaps_ss = kem.decapsulate(&sk, &ct)?;
+            timings[2] += start.elapsed();
+
+            if decaps_ss != encaps_ss {
+                return Err(Error::Error);
+            }
+        }
+        rows.push(ComparisonRow {
+            algorithm,
+            sizes: kem.sizes(),
+            keypair: timings[0] / samples,
+            encapsulate: timings[1] / samples,
+            decapsulate: timings[2] / samples,
+        });
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compare() -> Result<()> {
+        crate::init();
+        let algorithms = [Algorithm::Kyber512, Algorithm::Saber, Algorithm::Hqc128];
+        let rows = compare(&algorithms, 2)?;
+        let measured: Vec<Algorithm> = rows.iter().map(|row| row.algorithm).collect();
+        let enabled: Vec<Algorithm> = algorithms
+            .iter()
+            .copied()
+            .filter(|alg| alg.is_enabled())
+            .collect();
+        assert_eq!(measured, enabled);
+        for row in &rows {
+            assert_eq!(row.sizes, row.algorithm.sizes());
+        }
+        assert!(compare(&[], 0)?.is_empty());
+        Ok(())
+    }
+}
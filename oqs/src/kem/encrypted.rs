@@ -0,0 +1,181 @@
+//! Key pairs with a passphrase-encrypted secret key
+//!
+//! [`KeyPair::to_encrypted`] derives a key from the passphrase with Argon2id and
+//! seals the secret key with XChaCha20-Poly1305; [`Kem::keypair_from_encrypted`]
+//! reverses this. The public key is stored in clear, but authenticated along with
+//! the rest of the header. Version 1 of the format is, in order:
+//!
+//! | Field             | Size     | Contents                                          |
+//! |-------------------|----------|---------------------------------------------------|
+//! | magic             | 4        | `b"OQSE"`                                         |
+//! | version           | 1        | `1`                                               |
+//! | salt              | 16       | random Argon2id salt                              |
+//! | nonce             | 24       | random XChaCha20-Poly1305 nonce                   |
+//! | pk length         | 4        | length `p` of the public key, big endian          |
+//! | public key        | `p`      |                                                   |
+//! | sealed secret key | `s + 16` | encrypted secret key and tag; all preceding bytes are the associated data |
+//!
+//! Argon2id runs with the default parameters of the `argon2` crate. The derived
+//! key is zeroized after use, and the decrypted secret key is owned by the
+//! returned [`KeyPair`], which scrubs it when dropped. The algorithm is not
+//! stored; decrypt with a [`Kem`] of the algorithm the key pair belongs to.
+//!
+//! This module requires the `encrypted-keys` feature.
+use alloc::vec::Vec;
+
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use zeroize::Zeroizing;
+
+use super::{Kem, KeyPair, PublicKey, SecretKey};
+use crate::*;
+
+/// Identifies the format
+pub const MAGIC: [u8; 4] = *b"OQSE";
+
+/// The version of the format written by [`KeyPair::to_encrypted`]
+pub const VERSION: u8 = 1;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+/// Length of everything before the public key
+const HEADER_LEN: usize = 4 + 1 + SALT_LEN + NONCE_LEN + 4;
+
+/// Derive the cipher for `passphrase` and `salt`
+fn cipher(passphrase: &[u8], salt: &[u8]) -> XChaCha20Poly1305 {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::default()
+        .hash_password_into(passphrase, salt, &mut key[..])
+        .expect("Argon2 accepts any passphrase with a 16-byte salt");
+    XChaCha20Poly1305::new_from_slice(&key[..]).expect("the key has the right length")
+}
+
+impl KeyPair {
+    /// Serialize the key pair with the secret key encrypted under `passphrase`
+    ///
+    /// See the [module documentation](self) for the format. Every call uses a
+    /// fresh salt and nonce, so encrypting the same key pair twice gives different
+    /// results. Deriving the key is deliberately slow.
+    pub fn to_encrypted(&self, passphrase: &[u8]) -> Vec<u8> {
+        let mut buf =
+            Vec::with_capacity(HEADER_LEN + self.public.len() + self.secret.len() + TAG_LEN);
+        buf.extend_from_slice(&MAGIC);
+        buf.push(VERSION);
+        let mut salt_and_nonce = [0u8; SALT_LEN + NONCE_LEN];
+        unsafe {
+            crate::ffi::rand::OQS_randombytes(salt_and_nonce.as_mut_ptr(), salt_and_nonce.len())
+        };
+        buf.extend_from_slice(&salt_and_nonce);
+        buf.extend_from_slice(&(self.public.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&self.public.bytes);
+
+        let (salt, nonce) = salt_and_nonce.split_at(SALT_LEN);
+        let payload = Payload {
+            msg: &self.secret.bytes,
+            aad: &buf,
+        };
+        let sealed = cipher(passphrase, salt)
+            .encrypt(XNonce::from_slice(nonce), payload)
+            .expect("encryption cannot fail for inputs of this size");
+        buf.extend_from_slice(&sealed);
+        buf
+    }
+}
+
+impl Kem {
+    /// Decrypt a key pair serialized by [`KeyPair::to_encrypted`]
+    ///
+    /// Returns [`Error::InvalidFormat`] if the magic, version or structure is wrong,
+    /// [`Error::InvalidLength`] or [`Error::InvalidSecretKeyLength`] if the keys do
+    /// not have the lengths of this algorithm, and [`Error::DecryptionFailed`] if
+    /// the passphrase is wrong or the data was modified.
+    pub fn keypair_from_encrypted(&self, buf: &[u8], passphrase: &[u8]) -> Result<KeyPair> {
+        if buf.len() < HEADER_LEN || buf[..MAGIC.len()] != MAGIC || buf[MAGIC.len()] != VERSION {
+            return Err(Error::InvalidFormat);
+        }
+        let salt = &buf[MAGIC.len() + 1..MAGIC.len() + 1 + SALT_LEN];
+        let nonce = &buf[MAGIC.len() + 1 + SALT_LEN..HEADER_LEN - 4];
+        let pk_len = u32::from_be_bytes([
+            buf[HEADER_LEN - 4],
+            buf[HEADER_LEN - 3],
+            buf[HEADER_LEN - 2],
+            buf[HEADER_LEN - 1],
+        ]) as usize;
+        if pk_len != self.length_public_key() {
+            return Err(Error::InvalidLength {
+                expected: self.length_public_key(),
+                got: pk_len,
+            });
+        }
+        if buf.len() < HEADER_LEN + pk_len + TAG_LEN {
+            return Err(Error::InvalidFormat);
+        }
+        let (header, sealed) = buf.split_at(HEADER_LEN + pk_len);
+        if sealed.len() - TAG_LEN != self.length_secret_key() {
+            return Err(Error::InvalidSecretKeyLength {
+                expected: self.length_secret_key(),
+                got: sealed.len() - TAG_LEN,
+            });
+        }
+
+        let payload = Payload {
+            msg: sealed,
+            aad: header,
+        };
+        let secret = cipher(passphrase, salt)
+            .decrypt(XNonce::from_slice(nonce), payload)
+            .map_err(|_| Error::DecryptionFailed)?;
+        Ok(KeyPair {
+            public: PublicKey {
+                bytes: header[HEADER_LEN..].to_vec(),
+            },
+            secret: SecretKey { bytes: secret },
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kem::Algorithm;
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_encrypted_round_trip() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let keypair = kem.keypair_struct()?;
+        let blob = keypair.to_encrypted(b"correct horse");
+        assert_ne!(blob, keypair.to_encrypted(b"correct horse"));
+        assert_eq!(
+            blob.len(),
+            HEADER_LEN + kem.length_public_key() + kem.length_secret_key() + TAG_LEN
+        );
+
+        let decrypted = kem.keypair_from_encrypted(&blob, b"correct horse")?;
+        assert_eq!(decrypted, keypair);
+
+        assert!(matches!(
+            kem.keypair_from_encrypted(&blob, b"battery staple"),
+            Err(Error::DecryptionFailed)
+        ));
+        // The public key is authenticated
+        let mut tampered = blob.clone();
+        tampered[HEADER_LEN] ^= 1;
+        assert!(matches!(
+            kem.keypair_from_encrypted(&tampered, b"correct horse"),
+            Err(Error::DecryptionFailed)
+        ));
+        assert!(matches!(
+            kem.keypair_from_encrypted(&blob[..blob.len() - 1], b"correct horse"),
+            Err(Error::InvalidSecretKeyLength { .. })
+        ));
+        assert!(matches!(
+            kem.keypair_from_encrypted(b"OQSK", b"correct horse"),
+            Err(Error::InvalidFormat)
+        ));
+        Ok(())
+    }
+}
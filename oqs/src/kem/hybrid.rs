@@ -0,0 +1,267 @@
+//! Hybrid KEMs combining X25519 with a post-quantum KEM
+//!
+//! A [`HybridKem`] runs X25519 and a post-quantum KEM side by side, so the
+//! shared secret stays secure as long as either of them is. All objects are
+//! the concatenation of the X25519 part and the post-quantum part, in that order:
+//!
+//! * public key: the 32-byte X25519 public key, then the post-quantum public key;
+//! * secret key: the 32-byte X25519 secret key, then the post-quantum secret key;
+//! * ciphertext: the 32-byte ephemeral X25519 public key, then the post-quantum ciphertext;
+//! * shared secret: the 32-byte X25519 shared secret, then the post-quantum shared secret.
+//!
+//! With Kyber768, this is the wire format of the TLS 1.3 group
+//! `X25519Kyber768Draft00` (draft-tls-westerbaan-xyber768d00), see
+//! [`tls_x25519_kyber768`]. The public key is the client's key share, the
+//! ciphertext the server's, and the shared secret the input to the TLS key schedule.
+//!
+//! This module requires the `hybrid` feature.
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+use super::*;
+
+/// Length of X25519 keys and shared secrets
+const X25519_LEN: usize = 32;
+
+/// The TLS 1.3 `NamedGroup` codepoint of `X25519Kyber768Draft00`
+pub const TLS_X25519_KYBER768_DRAFT00: u16 = 0x6399;
+
+/// X25519 combined with a post-quantum KEM, see the [module documentation](self)
+pub struct HybridKem {
+    kem: Kem,
+}
+
+/// X25519 with Kyber768 in the format of the TLS group `X25519Kyber768Draft00`
+///
+/// Returns [`Error::AlgorithmDisabled`] if Kyber768 is not enabled.
+pub fn tls_x25519_kyber768() -> Result<HybridKem> {
+    HybridKem::new(Algorithm::Kyber768)
+}
+
+impl HybridKem {
+    /// Combine X25519 with `algorithm`
+    ///
+    /// For algorithms that need [`Kem::init`], call it on [`HybridKem::post_quantum`].
+    pub fn new(algorithm: Algorithm) -> Result<Self> {
+        Ok(HybridKem {
+            kem: Kem::new(algorithm)?,
+        })
+    }
+
+    /// The post-quantum half
+    pub fn post_quantum(&self) -> &Kem {
+        &self.kem
+    }
+
+    /// Get the length of a public key
+    pub fn length_public_key(&self) -> usize {
+        X25519_LEN + self.kem.length_public_key()
+    }
+
+    /// Get the length of a secret key
+    pub fn length_secret_key(&self) -> usize {
+        X25519_LEN + self.kem.length_secret_key()
+    }
+
+    /// Get the length of a ciphertext
+    pub fn length_ciphertext(&self) -> usize {
+        X25519_LEN + self.kem.length_ciphertext()
+    }
+
+    /// Get the length of a shared secret
+    pub fn length_shared_secret(&self) -> usize {
+        X25519_LEN + self.kem.length_shared_secret()
+    }
+
+    /// Generate a new keypair
+    pub fn keypair(&self) -> Result<(PublicKey, SecretKey)> {
+        let mut secret = random_scalar();
+        let public = x25519_dalek::x25519(secret, x25519_dalek::X25519_BASEPOINT_BYTES);
+        let (pq_pk, mut pq_sk) = self.kem.keypair()?;
+
+        let mut pk = Vec::with_capacity(self.length_public_key());
+        pk.extend_from_slice(&public);
+        pk.extend_from_slice(&pq_pk.bytes);
+        let mut sk = alloc::vec![0; self.length_secret_key()];
+        sk[..X25519_LEN].copy_from_slice(&secret);
+        sk[X25519_LEN..].copy_from_slice(&pq_sk.bytes);
+        crate::util::scrub(&mut secret);
+        crate::util::scrub(&mut pq_sk.bytes);
+        Ok((PublicKey { bytes: pk }, SecretKey { bytes: sk }))
+    }
+
+    /// Encapsulate to a public key
+    ///
+    /// Returns [`Error::InvalidLength`] if the public key has the wrong length,
+    /// and [`Error::InvalidFormat`] if its X25519 part is a low-order point.
+    pub fn encapsulate<'a, P: Into<PublicKeyRef<'a>>>(
+        &self,
+        pk: P,
+    ) -> Result<(Ciphertext, SharedSecret)> {
+        let mut ephemeral = random_scalar();
+        let result = self.encapsulate_with_ephemeral(pk.into(), &ephemeral);
+        crate::util::scrub(&mut ephemeral);
+        result
+    }
+
+    fn encapsulate_with_ephemeral(
+        &self,
+        pk: PublicKeyRef,
+        ephemeral: &[u8; X25519_LEN],
+    ) -> Result<(Ciphertext, SharedSecret)> {
+        check_length(self.length_public_key(), pk.bytes)?;
+        let (peer, pq_pk) = pk.bytes.split_at(X25519_LEN);
+        let mut ss = alloc::vec![0; self.length_shared_secret()];
+        diffie_hellman(ephemeral, peer, &mut ss[..X25519_LEN])?;
+        let (pq_ct, pq_ss) = self.kem.encapsulate(PublicKeyRef::new(pq_pk))?;
+        ss[X25519_LEN..].copy_from_slice(&pq_ss.bytes);
+
+        let mut ct = Vec::with_capacity(self.length_ciphertext());
+        ct.extend_from_slice(&x25519_dalek::x25519(
+            *ephemeral,
+            x25519_dalek::X25519_BASEPOINT_BYTES,
+        ));
+        ct.extend_from_slice(&pq_ct.bytes);
+        Ok((Ciphertext { bytes: ct }, SharedSecret { bytes: ss }))
+    }
+
+    /// Decapsulate a ciphertext
+    ///
+    /// Returns [`Error::InvalidSecretKeyLength`] or [`Error::InvalidCiphertextLength`]
+    /// for inputs of the wrong length, and [`Error::InvalidFormat`] if the X25519
+    /// part of the ciphertext is a low-order point.
+    pub fn decapsulate<'a, 'b, S: Into<SecretKeyRef<'a>>, C: Into<CiphertextRef<'b>>>(
+        &self,
+        sk: S,
+        ct: C,
+    ) -> Result<SharedSecret> {
+        let (sk, ct) = (sk.into(), ct.into());
+        if sk.bytes.len() != self.length_secret_key() {
+            return Err(Error::InvalidSecretKeyLength {
+                expected: self.length_secret_key(),
+                got: sk.bytes.len(),
+            });
+        }
+        if ct.bytes.len() != self.length_ciphertext() {
+            return Err(Error::InvalidCiphertextLength {
+                expected: self.length_ciphertext(),
+                got: ct.bytes.len(),
+            });
+        }
+        let (secret, pq_sk) = sk.bytes.split_at(X25519_LEN);
+        let (peer, pq_ct) = ct.bytes.split_at(X25519_LEN);
+        let mut secret: [u8; X25519_LEN] = secret.try_into().unwrap();
+        let mut ss = SharedSecret {
+            bytes: alloc::vec![0; self.length_shared_secret()],
+        };
+        let result = diffie_hellman(&secret, peer, &mut ss.bytes[..X25519_LEN]);
+        crate::util::scrub(&mut secret);
+        result?;
+        let pq_ss = self
+            .kem
+            .decapsulate(SecretKeyRef::new(pq_sk), CiphertextRef::new(pq_ct))?;
+        ss.bytes[X25519_LEN..].copy_from_slice(&pq_ss.bytes);
+        Ok(ss)
+    }
+}
+
+/// A fresh X25519 secret key from the liboqs random number generator
+fn random_scalar() -> [u8; X25519_LEN] {
+    let mut scalar = [0; X25519_LEN];
+    unsafe { crate::ffi::rand::OQS_randombytes(scalar.as_mut_ptr(), scalar.len()) };
+    scalar
+}
+
+/// Write the X25519 shared secret of `secret` and `peer` to `out`
+///
+/// Rejects an all-zero result, which a low-order `peer` point produces, as TLS requires.
+fn diffie_hellman(secret: &[u8; X25519_LEN], peer: &[u8], out: &mut [u8]) -> Result<()> {
+    let mut shared = x25519_dalek::x25519(*secret, peer.try_into().unwrap());
+    out.copy_from_slice(&shared);
+    let nonzero = shared.iter().fold(0, |acc, &byte| acc | byte);
+    crate::util::scrub(&mut shared);
+    if nonzero == 0 {
+        return Err(Error::InvalidFormat);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn from_hex(hex: &str) -> [u8; X25519_LEN] {
+        let mut out = [0; X25519_LEN];
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+        }
+        out
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_round_trip() -> Result<()> {
+        crate::init();
+        let kem = tls_x25519_kyber768()?;
+        let (pk, sk) = kem.keypair()?;
+        assert_eq!(pk.len(), 32 + 1184);
+        let (ct, ss) = kem.encapsulate(&pk)?;
+        assert_eq!(ct.len(), 32 + 1088);
+        assert_eq!(ss.len(), 64);
+        assert_eq!(kem.decapsulate(&sk, &ct)?, ss);
+        Ok(())
+    }
+
+    /// The X25519 part follows RFC 7748, section 6.1, and comes first
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_x25519_vector() -> Result<()> {
+        crate::init();
+        let kem = tls_x25519_kyber768()?;
+        let alice_sk = from_hex("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+        let alice_pk = from_hex("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a");
+        let bob_sk = from_hex("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb");
+        let bob_pk = from_hex("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f");
+        let shared = from_hex("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742");
+
+        let (pq_pk, pq_sk) = kem.post_quantum().keypair()?;
+        let pk = PublicKey {
+            bytes: [&bob_pk[..], pq_pk.as_ref()].concat(),
+        };
+        let sk = SecretKey {
+            bytes: [&bob_sk[..], pq_sk.as_ref()].concat(),
+        };
+        let (ct, ss) = kem.encapsulate_with_ephemeral(PublicKeyRef::from(&pk), &alice_sk)?;
+        assert_eq!(ct.as_ref()[..32], alice_pk);
+        assert_eq!(ss.as_ref()[..32], shared);
+        assert_eq!(kem.decapsulate(&sk, &ct)?, ss);
+
+        // A low-order point yields an all-zero X25519 secret
+        let mut bad = ct.into_vec();
+        bad[..32].copy_from_slice(&[0; 32]);
+        assert!(matches!(
+            kem.decapsulate(&sk, CiphertextRef::new(&bad)),
+            Err(Error::InvalidFormat)
+        ));
+        Ok(())
+    }
+
+    /// A fixed `X25519Kyber768Draft00` vector built from RFC 7748, section 6.1,
+    /// and the Kyber768 known-answer vector in `tests/kats`
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_x25519_kyber768_vector() -> Result<()> {
+        crate::init();
+        let kem = tls_x25519_kyber768()?;
+        let [_, pq_sk, pq_ct, pq_ss] = super::super::test::kyber768_kat();
+        let bob_sk = from_hex("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb");
+        let alice_pk = from_hex("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a");
+        let shared = from_hex("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742");
+
+        let sk = [&bob_sk[..], &pq_sk].concat();
+        let ct = [&alice_pk[..], &pq_ct].concat();
+        let ss = kem.decapsulate(SecretKeyRef::new(&sk), CiphertextRef::new(&ct))?;
+        assert_eq!(ss.as_ref(), &[&shared[..], &pq_ss].concat()[..]);
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+//! Decapsulation with memory-mapped secret keys
+//!
+//! This module requires the `mmap` feature.
+use std::fs::File;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use super::*;
+
+impl Kem {
+    /// Decapsulate `ct` with the secret key stored in the file at `sk_path`
+    ///
+    /// The file is mapped read-only and passed to liboqs without copying it
+    /// into a `Vec`, which saves loading megabyte-sized keys such as those of
+    /// Classic McEliece for every decapsulation. The file must contain exactly
+    /// the secret key; its length is checked against [`Kem::length_secret_key`]
+    /// before mapping, and [`Error::InvalidSecretKeyLength`] is returned if it
+    /// differs. I/O failures are returned as [`Error::Io`].
+    ///
+    /// The mapping only lives for the duration of this call and is unmapped
+    /// before it returns; the shared secret is an owned copy. The key pages may
+    /// stay in the page cache after that, and the key is not scrubbed from them.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated, by this or any other process,
+    /// until this call returns. Otherwise the key changes underneath liboqs while
+    /// it reads it, which is undefined behaviour, and reading a truncated part of
+    /// the mapping raises `SIGBUS`.
+    pub unsafe fn decapsulate_mmap<'a, C: Into<CiphertextRef<'a>>>(
+        &self,
+        sk_path: &Path,
+        ct: C,
+    ) -> Result<SharedSecret> {
+        let file = File::open(sk_path).map_err(Error::Io)?;
+        let len = file.metadata().map_err(Error::Io)?.len();
+        if len != self.length_secret_key() as u64 {
+            return Err(Error::InvalidSecretKeyLength {
+                expected: self.length_secret_key(),
+                got: len as usize,
+            });
+        }
+        // SAFETY: the mapping is read-only and dropped before returning; the
+        // caller guarantees that the file is not modified meanwhile
+        let map = unsafe { Mmap::map(&file) }.map_err(Error::Io)?;
+        self.decapsulate(SecretKeyRef::new(&map), ct)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_decapsulate_mmap() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (pk, sk) = kem.keypair()?;
+        let (ct, ss) = kem.encapsulate(&pk)?;
+
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("oqs-mmap-test-{}.key", std::process::id()));
+        std::fs::write(&path, sk.as_ref()).unwrap();
+        // SAFETY: nothing else writes to the file of this test
+        let result = unsafe { kem.decapsulate_mmap(&path, &ct) };
+        std::fs::write(&path, &sk.as_ref()[1..]).unwrap();
+        let short = unsafe { kem.decapsulate_mmap(&path, &ct) };
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(result?, ss);
+        assert!(matches!(
+            short,
+            Err(Error::InvalidSecretKeyLength { got: 1631, .. })
+        ));
+        assert!(matches!(
+            unsafe { kem.decapsulate_mmap(&path, &ct) },
+            Err(Error::Io(_))
+        ));
+        Ok(())
+    }
+}
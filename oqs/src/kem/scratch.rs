@@ -0,0 +1,148 @@
+//! Reuse output buffers across KEM operations
+//!
+//! Inside [`with_arena`], [`Kem::keypair`], [`Kem::encapsulate`],
+//! [`Kem::decapsulate`] and the other allocating operations of this thread take
+//! their output buffers from the arena instead of the allocator, as long as it
+//! holds one that is large enough. Give buffers back with [`Arena::recycle`]
+//! once you are done with them:
+//!
+//! ```
+//! # use oqs::kem::{self, Kem};
+//! # #[cfg(feature = "kyber")]
+//! # fn main() -> oqs::Result<()> {
+//! oqs::init();
+//! let kem = Kem::new(kem::Algorithm::Kyber512)?;
+//! let (pk, sk) = kem.keypair()?;
+//! kem::scratch::with_arena(|arena| {
+//!     for _ in 0..100 {
+//!         let (ct, ss) = kem.encapsulate(&pk)?;
+//!         assert_eq!(kem.decapsulate(&sk, &ct)?, ss);
+//!         // From the second iteration on, no allocations
+//!         arena.recycle(ct.into_vec());
+//!         arena.recycle(ss.into_vec());
+//!     }
+//!     Ok(())
+//! })
+//! # }
+//! # #[cfg(not(feature = "kyber"))]
+//! # fn main() {}
+//! ```
+//!
+//! The buffers handed out are ordinary owned buffers: they stay valid after the
+//! closure returns, and dropping them instead of recycling them just frees them.
+//! Recycled buffers are scrubbed at once, as they may hold secrets, and freed when
+//! the closure returns. Whether this is faster depends on the allocator; the
+//! `throughput` example compares both.
+//!
+//! This module requires the `std` feature.
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+std::thread_local! {
+    /// The arenas of the enclosing [`with_arena`] calls, innermost last
+    static ARENAS: RefCell<Vec<Rc<Arena>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Buffers to reuse for the outputs of KEM operations, see [`with_arena`]
+#[derive(Debug, Default)]
+pub struct Arena {
+    free: RefCell<Vec<Vec<u8>>>,
+}
+
+impl Arena {
+    /// Hand `buf` back for reuse by later operations
+    ///
+    /// `buf` is scrubbed before it is stored. Use the `into_vec` method of the
+    /// key, ciphertext and secret types to get their buffer.
+    pub fn recycle(&self, mut buf: Vec<u8>) {
+        crate::util::scrub(&mut buf);
+        buf.clear();
+        self.free.borrow_mut().push(buf);
+    }
+
+    /// The number of buffers available for reuse
+    pub fn available(&self) -> usize {
+        self.free.borrow().len()
+    }
+
+    /// Take a stored buffer with room for `len` bytes, zeroed and of length `len`
+    fn take(&self, len: usize) -> Option<Vec<u8>> {
+        let mut free = self.free.borrow_mut();
+        let index = free.iter().rposition(|buf| buf.capacity() >= len)?;
+        let mut buf = free.swap_remove(index);
+        buf.resize(len, 0);
+        Some(buf)
+    }
+}
+
+/// Removes the arena of a [`with_arena`] call again, also when unwinding
+struct ArenaGuard;
+
+impl Drop for ArenaGuard {
+    fn drop(&mut self) {
+        ARENAS.with(|arenas| arenas.borrow_mut().pop());
+    }
+}
+
+/// Run `f` with an [`Arena`] that the KEM operations of this thread reuse buffers from
+///
+/// Calls can be nested; operations use the innermost arena.
+pub fn with_arena<R>(f: impl FnOnce(&Arena) -> R) -> R {
+    let arena = Rc::new(Arena::default());
+    ARENAS.with(|arenas| arenas.borrow_mut().push(arena.clone()));
+    let _guard = ArenaGuard;
+    f(&arena)
+}
+
+/// A zeroed buffer of length `len` from the innermost arena of this thread, if any
+pub(crate) fn take(len: usize) -> Option<Vec<u8>> {
+    ARENAS
+        .try_with(|arenas| arenas.borrow().last().and_then(|arena| arena.take(len)))
+        .ok()
+        .flatten()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_take() {
+        assert!(take(16).is_none());
+        with_arena(|arena| {
+            arena.recycle(vec![0xff; 32]);
+            assert!(take(64).is_none());
+            assert_eq!(take(16).unwrap(), [0; 16]);
+            assert_eq!(arena.available(), 0);
+            with_arena(|inner| {
+                arena.recycle(vec![0xff; 32]);
+                assert!(take(16).is_none());
+                inner.recycle(vec![0xff; 32]);
+                assert!(take(16).is_some());
+            });
+            assert!(take(16).is_some());
+        });
+        assert!(take(16).is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_arena_avoids_allocations() -> crate::Result<()> {
+        use crate::alloc_counter::count_allocations;
+        use crate::kem::{Algorithm, Kem};
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (pk, sk) = kem.keypair()?;
+        let (ct, ss) = kem.encapsulate(&pk)?;
+        let kept = with_arena(|arena| -> crate::Result<_> {
+            arena.recycle(kem.decapsulate(&sk, &ct)?.into_vec());
+            let (result, allocations) = count_allocations(|| kem.decapsulate(&sk, &ct));
+            assert_eq!(allocations, 0, "decapsulate allocated");
+            result
+        })?;
+        // Buffers from the arena outlive it
+        assert_eq!(kept, ss);
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+//! Hardened storage for long-term secret keys
+//!
+//! [`SecureBytes`] keeps a secret key in a buffer that is locked into memory,
+//! so it is not written to swap, scrubbed when dropped and never printed.
+//! Create one with [`SecretKey::into_secure`] and pass `&SecureBytes` wherever a
+//! secret key is expected, e.g. to [`Kem::decapsulate`].
+//!
+//! This module requires the `secure-alloc` feature.
+use alloc::vec::Vec;
+use core::ptr::NonNull;
+use std::alloc::{alloc_zeroed, dealloc, handle_alloc_error, Layout};
+
+use super::{SecretKey, SecretKeyRef};
+
+/// A fixed-size buffer for secret key material
+///
+/// The buffer never grows, so its contents are never copied by a reallocation.
+/// It starts on a page boundary and is padded to a whole number of pages, so it
+/// shares no page with other allocations. On Unix these pages are locked with
+/// `mlock`; this can fail when it exceeds the limit on locked memory
+/// (`RLIMIT_MEMLOCK`), in which case the buffer is used unlocked, see
+/// [`SecureBytes::is_locked`]. On other platforms it is never locked.
+/// The contents are scrubbed before the buffer is unlocked and freed, and
+/// unlocking cannot affect memory outside it.
+/// `Debug` only shows the length, and there is deliberately no `Clone`.
+pub struct SecureBytes {
+    ptr: NonNull<u8>,
+    len: usize,
+    layout: Layout,
+    locked: bool,
+}
+
+// SAFETY: `SecureBytes` owns its allocation like a `Box<[u8]>` does
+unsafe impl Send for SecureBytes {}
+// SAFETY: shared references only allow reading the buffer
+unsafe impl Sync for SecureBytes {}
+
+impl SecureBytes {
+    /// Move `bytes` into a new locked buffer, scrubbing the original
+    fn new(mut bytes: Vec<u8>) -> SecureBytes {
+        let page = page_size();
+        let size = bytes.len().max(1).div_ceil(page) * page;
+        let layout = Layout::from_size_align(size, page).expect("invalid buffer layout");
+        // SAFETY: `layout` has a non-zero size
+        let ptr = NonNull::new(unsafe { alloc_zeroed(layout) })
+            .unwrap_or_else(|| handle_alloc_error(layout));
+        let mut secure = SecureBytes {
+            ptr,
+            len: bytes.len(),
+            layout,
+            locked: false,
+        };
+        secure.locked = lock(ptr.as_ptr(), size);
+        secure.as_mut_slice().copy_from_slice(&bytes);
+        crate::util::scrub(&mut bytes);
+        secure
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        // SAFETY: `ptr` is an allocation of at least `len` initialized bytes
+        unsafe { core::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        // SAFETY: as in `as_slice`, and `self` is borrowed mutably
+        unsafe { core::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Returns true if the buffer is locked into memory
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Length in bytes
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl Drop for SecureBytes {
+    fn drop(&mut self) {
+        crate::util::scrub(self.as_mut_slice());
+        if self.locked {
+            unlock(self.ptr.as_ptr(), self.layout.size());
+        }
+        // SAFETY: `ptr` was allocated with `layout` in `SecureBytes::new`
+        unsafe { dealloc(self.ptr.as_ptr(), self.layout) };
+    }
+}
+
+impl core::fmt::Debug for SecureBytes {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "SecureBytes([REDACTED; {} bytes])", self.len)
+    }
+}
+
+impl<'a> From<&'a SecureBytes> for SecretKeyRef<'a> {
+    fn from(secure: &'a SecureBytes) -> SecretKeyRef<'a> {
+        SecretKeyRef::new(secure.as_slice())
+    }
+}
+
+impl SecretKey {
+    /// Move the secret key into a [`SecureBytes`] buffer
+    ///
+    /// The key is copied into the locked buffer once and the original buffer is
+    /// scrubbed, so no unlocked copy remains. Requires the `secure-alloc` feature.
+    pub fn into_secure(self) -> SecureBytes {
+        SecureBytes::new(self.bytes)
+    }
+}
+
+#[cfg(unix)]
+fn page_size() -> usize {
+    // SAFETY: `sysconf` has no preconditions
+    match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as usize,
+        _ => 4096,
+    }
+}
+
+#[cfg(unix)]
+fn lock(ptr: *const u8, size: usize) -> bool {
+    // SAFETY: `ptr` is a page-aligned allocation of `size` bytes
+    unsafe { libc::mlock(ptr as *const _, size) == 0 }
+}
+
+#[cfg(unix)]
+fn unlock(ptr: *const u8, size: usize) {
+    // SAFETY: the pages were locked by `lock`, are still allocated and hold
+    // nothing but this buffer
+    unsafe { libc::munlock(ptr as *const _, size) };
+}
+
+#[cfg(not(unix))]
+fn page_size() -> usize {
+    4096
+}
+
+#[cfg(not(unix))]
+fn lock(_ptr: *const u8, _size: usize) -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+fn unlock(_ptr: *const u8, _size: usize) {}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_decapsulate_with_secure_bytes() -> crate::Result<()> {
+        use crate::kem::{Algorithm, Kem};
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (pk, sk) = kem.keypair()?;
+        let (ct, ss) = kem.encapsulate(&pk)?;
+        let secure = sk.into_secure();
+        assert_eq!(secure.len(), kem.length_secret_key());
+        assert_eq!(kem.decapsulate(&secure, &ct)?, ss);
+        assert_eq!(
+            format!("{:?}", secure),
+            "SecureBytes([REDACTED; 1632 bytes])"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_page_aligned() {
+        use super::*;
+        let secure = SecureBytes::new(alloc::vec![1; 10]);
+        let page = page_size();
+        assert_eq!(secure.ptr.as_ptr() as usize % page, 0);
+        assert_eq!(secure.layout.size(), page);
+        assert_eq!(secure.as_slice(), [1; 10]);
+        assert_eq!(SecureBytes::new(Vec::new()).len(), 0);
+    }
+}
@@ -0,0 +1,295 @@
+//! Shamir secret sharing of secret keys
+//!
+//! [`SecretKey::split`] splits a secret key into `n` shares, any `k` of which
+//! recover it through [`Kem::secret_key_from_shares`]; fewer than `k` shares
+//! reveal nothing about the key. Every byte of the key is shared separately
+//! with a random polynomial of degree `k - 1` over GF(2^8).
+//!
+//! The field arithmetic does not use lookup tables, so its timing does not
+//! depend on the secret bytes. The random coefficients are drawn from the
+//! liboqs random number generator and scrubbed after use.
+//!
+//! ```
+//! # if !cfg!(feature = "kyber") { return; }
+//! use oqs::kem::{Algorithm, Kem};
+//! oqs::init();
+//! let kem = Kem::new(Algorithm::Kyber512).unwrap();
+//! let (_, sk) = kem.keypair().unwrap();
+//! let shares = sk.split(2, 3);
+//! let recovered = kem.secret_key_from_shares(&shares[1..]).unwrap();
+//! assert_eq!(recovered, sk);
+//! ```
+//!
+//! This module requires the `shares` feature.
+use alloc::vec::Vec;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{Kem, SecretKey};
+use crate::*;
+
+/// One share of a secret key, see [`SecretKey::split`]
+///
+/// Optional support for `serde` if that feature is enabled.
+/// The `Debug` output leaves out the share bytes.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Share {
+    index: u8,
+    threshold: u8,
+    bytes: Vec<u8>,
+}
+
+impl Share {
+    /// The position of this share, from 1 up to the number of shares
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// The number of shares needed to recover the secret key
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// Serialize as the index, the threshold and the share bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(2 + self.bytes.len());
+        buf.push(self.index);
+        buf.push(self.threshold);
+        buf.extend_from_slice(&self.bytes);
+        buf
+    }
+
+    /// Parse the output of [`Share::to_bytes`]
+    ///
+    /// Returns [`Error::InvalidFormat`] if the index or threshold is zero.
+    pub fn from_bytes(buf: &[u8]) -> Result<Share> {
+        match buf {
+            [index, threshold, bytes @ ..] if *index != 0 && *threshold != 0 => Ok(Share {
+                index: *index,
+                threshold: *threshold,
+                bytes: bytes.to_vec(),
+            }),
+            _ => Err(Error::InvalidFormat),
+        }
+    }
+}
+
+impl core::fmt::Debug for Share {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Share")
+            .field("index", &self.index)
+            .field("threshold", &self.threshold)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SecretKey {
+    /// Split the secret key into `n` shares, any `k` of which recover it
+    ///
+    /// # Panics
+    /// If `k` is zero or larger than `n`.
+    pub fn split(&self, k: u8, n: u8) -> Vec<Share> {
+        assert!(
+            k >= 1 && k <= n,
+            "need 1 <= k <= n, got k = {}, n = {}",
+            k,
+            n
+        );
+        let len = self.bytes.len();
+        // Coefficients of x^1 up to x^(k - 1) for every byte of the key
+        let mut coefficients = alloc::vec![0; (k as usize - 1) * len];
+        unsafe { crate::ffi::rand::OQS_randombytes(coefficients.as_mut_ptr(), coefficients.len()) };
+        let coefficients = crate::util::ScrubGuard(&mut coefficients);
+
+        (1..=n)
+            .map(|x| {
+                let bytes = (0..len)
+                    .map(|i| {
+                        // Horner's rule, from the highest coefficient down to the key byte
+                        let mut y = 0;
+                        for degree in (1..k as usize).rev() {
+                            y = gf_mul(y, x) ^ coefficients.0[(degree - 1) * len + i];
+                        }
+                        gf_mul(y, x) ^ self.bytes[i]
+                    })
+                    .collect();
+                Share {
+                    index: x,
+                    threshold: k,
+                    bytes,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Kem {
+    /// Recover a secret key from the shares created by [`SecretKey::split`]
+    ///
+    /// Returns [`Error::InsufficientShares`] if fewer shares than the threshold
+    /// are given, [`Error::InvalidFormat`] if the shares disagree on their
+    /// threshold or length or share an index, and [`Error::InvalidSecretKeyLength`]
+    /// if the shares are not of a secret key of this algorithm.
+    pub fn secret_key_from_shares(&self, shares: &[Share]) -> Result<SecretKey> {
+        let threshold = shares.first().map_or(1, |share| share.threshold);
+        if shares.len() < threshold as usize {
+            return Err(Error::InsufficientShares {
+                needed: threshold as usize,
+                got: shares.len(),
+            });
+        }
+        let shares = &shares[..threshold as usize];
+        let len = shares[0].bytes.len();
+        for (i, share) in shares.iter().enumerate() {
+            if share.threshold != threshold
+                || share.bytes.len() != len
+                || share.index == 0
+                || shares[..i].iter().any(|other| other.index == share.index)
+            {
+                return Err(Error::InvalidFormat);
+            }
+        }
+        if len != self.length_secret_key() {
+            return Err(Error::InvalidSecretKeyLength {
+                expected: self.length_secret_key(),
+                got: len,
+            });
+        }
+
+        let mut sk = SecretKey {
+            bytes: alloc::vec![0; len],
+        };
+        for (i, share) in shares.iter().enumerate() {
+            // Lagrange basis polynomial of this share, evaluated at zero
+            let mut basis = 1;
+            for (j, other) in shares.iter().enumerate() {
+                if i != j {
+                    basis = gf_mul(
+                        basis,
+                        gf_mul(other.index, gf_inv(other.index ^ share.index)),
+                    );
+                }
+            }
+            for (byte, &y) in sk.bytes.iter_mut().zip(&share.bytes) {
+                *byte ^= gf_mul(basis, y);
+            }
+        }
+        Ok(sk)
+    }
+}
+
+/// Multiply in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1, without branching on the operands
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    for _ in 0..8 {
+        product ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (carry & 0x1b);
+        b >>= 1;
+    }
+    product
+}
+
+/// Invert a nonzero element of GF(2^8), as a^254
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1;
+    let mut power = a;
+    for bit in 0..8 {
+        if (254u8 >> bit) & 1 == 1 {
+            result = gf_mul(result, power);
+        }
+        power = gf_mul(power, power);
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kem::Algorithm;
+
+    #[test]
+    fn test_gf_arithmetic() {
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+        assert_eq!(gf_mul(0x57, 0x13), 0xfe);
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1, "inverse of {}", a);
+            assert_eq!(gf_mul(a, 1), a);
+            assert_eq!(gf_mul(a, 0), 0);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_split_and_recover() -> Result<()> {
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let (_, sk) = kem.keypair()?;
+        let shares = sk.split(3, 5);
+        assert_eq!(shares.len(), 5);
+        assert!(shares.iter().all(|share| share.threshold() == 3));
+
+        for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]].iter() {
+            let subset: Vec<Share> = subset.iter().map(|&i| shares[i].clone()).collect();
+            assert_eq!(kem.secret_key_from_shares(&subset)?, sk);
+        }
+        assert_eq!(kem.secret_key_from_shares(&shares)?, sk);
+
+        assert!(matches!(
+            kem.secret_key_from_shares(&shares[..2]),
+            Err(Error::InsufficientShares { needed: 3, got: 2 })
+        ));
+        // Ignoring the threshold, two shares interpolate a different key
+        let forged: Vec<Share> = shares[..2]
+            .iter()
+            .map(|share| Share {
+                threshold: 2,
+                ..share.clone()
+            })
+            .collect();
+        assert_ne!(kem.secret_key_from_shares(&forged)?, sk);
+
+        let duplicate = [shares[0].clone(), shares[0].clone(), shares[1].clone()];
+        assert!(matches!(
+            kem.secret_key_from_shares(&duplicate),
+            Err(Error::InvalidFormat)
+        ));
+
+        let single = sk.split(1, 1);
+        assert_eq!(single[0].bytes, sk.bytes);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(feature = "kyber", feature = "frodokem"))]
+    fn test_wrong_algorithm() -> Result<()> {
+        crate::init();
+        let kyber = Kem::new(Algorithm::Kyber512)?;
+        let frodo = Kem::new(Algorithm::FrodoKem640Aes)?;
+        let (_, sk) = kyber.keypair()?;
+        assert!(matches!(
+            frodo.secret_key_from_shares(&sk.split(2, 2)),
+            Err(Error::InvalidSecretKeyLength { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_share_bytes() {
+        let share = Share {
+            index: 2,
+            threshold: 3,
+            bytes: alloc::vec![1, 2, 3],
+        };
+        assert_eq!(share.to_bytes(), [2, 3, 1, 2, 3]);
+        assert_eq!(Share::from_bytes(&share.to_bytes()).unwrap(), share);
+        assert!(Share::from_bytes(&[0, 3, 1]).is_err());
+        assert!(Share::from_bytes(&[1]).is_err());
+        assert_eq!(
+            alloc::format!("{:?}", share),
+            "Share { index: 2, threshold: 3, .. }"
+        );
+    }
+}
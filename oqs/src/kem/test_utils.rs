@@ -0,0 +1,112 @@
+//! Reproducible key pairs for test fixtures
+//!
+//! This module requires the `test-utils` feature.
+use std::thread::{self, ThreadId};
+
+use sha3::{Digest, Sha3_256};
+
+use super::*;
+
+/// Domain separator of the randomness derived for [`Kem::keypair_for_test`]
+const LABEL: &[u8] = b"oqs-rs test keypair";
+
+/// Randomness derived from a test index with SHA3-256 in counter mode
+///
+/// liboqs has a single generator for the whole process, so other threads may draw
+/// from this one while it is installed. They get a separate stream, so that what
+/// the thread generating the key pair draws does not depend on them.
+struct TestRng {
+    index: u64,
+    owner: ThreadId,
+    blocks: u64,
+    other_blocks: u64,
+}
+
+impl crate::rand::Rng for TestRng {
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        let (stream, blocks) = if thread::current().id() == self.owner {
+            (0u8, &mut self.blocks)
+        } else {
+            (1u8, &mut self.other_blocks)
+        };
+        for chunk in dest.chunks_mut(32) {
+            let mut hasher = Sha3_256::new();
+            hasher.update(LABEL);
+            hasher.update([stream]);
+            hasher.update(self.index.to_be_bytes());
+            hasher.update(blocks.to_be_bytes());
+            *blocks += 1;
+            chunk.copy_from_slice(&hasher.finalize()[..chunk.len()]);
+        }
+    }
+}
+
+impl Kem {
+    /// Generate the key pair numbered `index`, the same one on every run
+    ///
+    /// **The keys are NOT secret.** Anyone can regenerate them from `index`, so
+    /// use them only as test fixtures, never to protect data.
+    ///
+    /// liboqs 0.7 has no deterministic key generation, so this runs [`Kem::keypair`]
+    /// with a generator derived from `index` installed through
+    /// [`rand::with_rng`](crate::rand::with_rng), and restores the previous generator
+    /// afterwards. Other threads calling into liboqs meanwhile draw predictable
+    /// randomness from it as well, but do not change the key pair. The key pair
+    /// for an index may change with the liboqs version, as the implementation
+    /// may draw the randomness differently.
+    pub fn keypair_for_test(&self, index: u64) -> Result<(PublicKey, SecretKey)> {
+        let rng = TestRng {
+            index,
+            owner: thread::current().id(),
+            blocks: 0,
+            other_blocks: 0,
+        };
+        crate::rand::with_rng(rng, || self.keypair())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rand::Rng;
+
+    #[test]
+    fn test_derived_randomness() {
+        // Computed with Python's hashlib.sha3_256, so the fixtures stay stable
+        let expected = [
+            0x9f, 0xf0, 0xff, 0xf5, 0xb9, 0xf7, 0x4d, 0x6e, 0x67, 0x0e, 0xcc, 0xdc, 0x8e, 0x1c,
+            0x2e, 0x06, 0x1b, 0x11, 0xc1, 0x44, 0x1d, 0x4f, 0xfc, 0xbc, 0x7f, 0x65, 0x59, 0x58,
+            0x47, 0x16, 0x60, 0xdc,
+        ];
+        let mut rng = TestRng {
+            index: 7,
+            owner: thread::current().id(),
+            blocks: 0,
+            other_blocks: 0,
+        };
+        let mut buf = [0u8; 40];
+        rng.fill_bytes(&mut buf);
+        assert_eq!(buf[..32], expected);
+        assert_eq!(rng.blocks, 2);
+        assert_eq!(rng.other_blocks, 0);
+    }
+
+    #[test]
+    #[cfg(feature = "kyber")]
+    fn test_keypair_for_test() -> Result<()> {
+        let _lock = crate::rand::test::RNG_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        crate::init();
+        let kem = Kem::new(Algorithm::Kyber512)?;
+        let first = kem.keypair_for_test(7)?;
+        assert_eq!(kem.keypair_for_test(7)?, first);
+        assert_ne!(kem.keypair_for_test(8)?.0, first.0);
+        // The generator is removed again
+        assert_ne!(kem.keypair()?.0, first.0);
+
+        let (ct, ss) = kem.encapsulate(&first.0)?;
+        assert_eq!(kem.decapsulate(&first.1, &ct)?, ss);
+        Ok(())
+    }
+}
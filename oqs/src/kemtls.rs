@@ -0,0 +1,107 @@
+//! Helpers for KEMTLS handshakes
+//!
+//! [`Transcript`] hashes the running handshake transcript with SHA-384.
+//! Every [`Transcript::update`] absorbs, in order:
+//!
+//! | Field       | Size | Contents                                  |
+//! |-------------|------|-------------------------------------------|
+//! | label len   | 8    | length `l` of the label, big endian       |
+//! | label       | `l`  |                                           |
+//! | data len    | 8    | length `d` of the data, big endian        |
+//! | data        | `d`  | a handshake message, public key, ...      |
+//!
+//! The lengths make the encoding unambiguous, so moving bytes between the label
+//! and the data, or between two updates, changes the hash.
+//!
+//! This module requires the `kemtls` feature.
+use sha2::{Digest, Sha384};
+
+/// Length of a transcript hash
+pub const TRANSCRIPT_HASH_LEN: usize = 48;
+
+/// A running SHA-384 hash of a KEMTLS handshake transcript
+///
+/// ```
+/// # use oqs::kemtls::Transcript;
+/// let mut transcript = Transcript::new();
+/// transcript.update(b"ClientHello", b"...");
+/// let after_hello = transcript.finalize();
+/// transcript.update(b"ServerHello", b"...");
+/// assert_ne!(transcript.finalize(), after_hello);
+/// ```
+#[derive(Clone)]
+pub struct Transcript {
+    hasher: Sha384,
+}
+
+impl Transcript {
+    /// Start an empty transcript
+    pub fn new() -> Self {
+        Transcript {
+            hasher: Sha384::new(),
+        }
+    }
+
+    /// Absorb `data` under `label`, see the [module documentation](self)
+    ///
+    /// `data` can be any buffer, such as a handshake message, a
+    /// [`PublicKey`](crate::kem::PublicKey) or a [`Ciphertext`](crate::kem::Ciphertext).
+    pub fn update<D: AsRef<[u8]>>(&mut self, label: &[u8], data: D) {
+        let data = data.as_ref();
+        self.hasher.update((label.len() as u64).to_be_bytes());
+        self.hasher.update(label);
+        self.hasher.update((data.len() as u64).to_be_bytes());
+        self.hasher.update(data);
+    }
+
+    /// The hash of everything absorbed so far
+    ///
+    /// The transcript can be updated further afterwards, to hash the next
+    /// stage of the handshake.
+    pub fn finalize(&self) -> [u8; TRANSCRIPT_HASH_LEN] {
+        let mut hash = [0u8; TRANSCRIPT_HASH_LEN];
+        hash.copy_from_slice(&self.hasher.clone().finalize());
+        hash
+    }
+}
+
+impl Default for Transcript {
+    fn default() -> Self {
+        Transcript::new()
+    }
+}
+
+impl core::fmt::Debug for Transcript {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Transcript").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_transcript() {
+        // Computed with Python's hashlib.sha384
+        let expected = [
+            0x96, 0x22, 0x18, 0x44, 0xb5, 0x40, 0x55, 0x0b, 0x77, 0x41, 0xab, 0x1b, 0x67, 0xb9,
+            0x3f, 0x17, 0xf5, 0xe6, 0x5c, 0x66, 0xf5, 0xdf, 0xc1, 0x20, 0xaa, 0x69, 0x4c, 0xf7,
+            0xb7, 0xa2, 0x09, 0xd9, 0x21, 0xf0, 0xe8, 0xfd, 0xc8, 0x7e, 0x29, 0x60, 0x7e, 0x9c,
+            0x92, 0x87, 0x50, 0x82, 0xa6, 0xa2,
+        ];
+        let mut transcript = Transcript::new();
+        transcript.update(b"ClientHello", b"hello");
+        transcript.update(b"public key", [1, 2, 3]);
+        let partial = transcript.finalize();
+        transcript.update(b"ciphertext", [4; 5]);
+        assert_eq!(transcript.finalize(), expected);
+        assert_ne!(partial, expected);
+
+        let mut shifted = Transcript::new();
+        shifted.update(b"ClientHell", b"ohello");
+        let mut unshifted = Transcript::new();
+        unshifted.update(b"ClientHello", b"hello");
+        assert_ne!(shifted.finalize(), unshifted.finalize());
+    }
+}
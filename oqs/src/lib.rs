@@ -4,6 +4,15 @@
 //!
 //! See the [`kem::Kem`](crate::kem::Kem) and [`sig::Sig`](crate::sig::Sig) structs for how to use this crate.
 //!
+//! The linked liboqs does not say whether an implementation runs in constant
+//! time, nor which implementation (reference or optimized) it picked for an
+//! algorithm. Its constant-time testing (the `OQS_ENABLE_TEST_CONSTANT_TIME`
+//! build option) only runs in its own test suite.
+//!
+//! liboqs runs every operation on the calling thread and has no setting for
+//! internal parallelism. To bound CPU usage, limit the number of threads calling
+//! into it instead.
+//!
 //! # Example: Some signed KEX
 //!
 //! This protocol has no replay protection!
@@ -49,7 +58,13 @@ use ffi::common::OQS_STATUS;
 /// Access the OQS ffi through this crate.
 pub use oqs_sys as ffi;
 
+pub use features::{build_info, BuildInfo};
+
+#[cfg(all(test, feature = "std"))]
+mod alloc_counter;
 mod macros;
+#[cfg(feature = "numa")]
+mod numa;
 
 /// Initialize liboqs
 ///
@@ -83,6 +98,21 @@ pub fn kem_deinit() {
     unsafe { ffi::kem::OQS_KEM_deinit() };
 }
 
+/// Free the global state of liboqs, the counterpart to [`init`]
+///
+/// Call this once at shutdown, after dropping all [`kem::Kem`]s, so leak checkers
+/// such as valgrind or ASan give a clean report. It frees the state built by
+/// [`kem::Kem::init`], such as the SIKE precomputation tables. With the ``std``
+/// feature, that state is left alone while initialized `Kem`s still use it; it is
+/// then freed when the last of them is dropped.
+///
+/// This is idempotent and may be called even if [`init`] never was.
+/// The linked liboqs predates `OQS_destroy`, which also releases the memory
+/// liboqs allocates through OpenSSL, so that memory stays allocated.
+pub fn destroy() -> Result<()> {
+    kem::destroy()
+}
+
 #[derive(Debug)]
 #[non_exhaustive]
 /// Possible errors
@@ -90,12 +120,144 @@ pub enum Error {
     /// Indicates an algorithm has been disabled
     AlgorithmDisabled,
     /// Generic error
+    ///
+    /// liboqs reports every failure other than one of OpenSSL as `OQS_ERROR`,
+    /// so there is no status code to carry. This crate also uses it for failures
+    /// that did not come from liboqs.
     Error,
     /// Error occurred in OpenSSL functions external to liboqs
     #[allow(clippy::upper_case_acronyms)]
     ErrorExternalOpenSSL,
     /// Invalid length of a public object
-    InvalidLength,
+    InvalidLength {
+        /// The length the algorithm requires
+        expected: usize,
+        /// The length that was provided
+        got: usize,
+    },
+    /// Invalid length of a ciphertext
+    InvalidCiphertextLength {
+        /// The length the algorithm requires
+        expected: usize,
+        /// The length that was provided
+        got: usize,
+    },
+    /// Invalid length of a secret key
+    InvalidSecretKeyLength {
+        /// The length the algorithm requires
+        expected: usize,
+        /// The length that was provided
+        got: usize,
+    },
+    /// The algorithm does not support the requested operation
+    OperationNotSupported,
+    /// A checksum did not match the data it was supplied with
+    ChecksumMismatch,
+    /// Serialized data is not in the expected format
+    InvalidFormat,
+    /// A secret key was used again although reuse protection is enabled
+    KeyReused,
+    /// The algorithm needs to be initialized with [`kem::Kem::init`] first
+    NotInitialized,
+    /// Fewer shares were given than needed to recover a secret
+    InsufficientShares {
+        /// The number of shares needed
+        needed: usize,
+        /// The number of shares that were given
+        got: usize,
+    },
+    /// A freshly generated key pair failed its pairwise consistency test
+    PairwiseConsistencyFailed,
+    /// The operation was cancelled by the caller
+    Cancelled,
+    /// A key is tagged with a different algorithm than the one in use
+    AlgorithmMismatch,
+    /// Decryption failed: the passphrase is wrong or the data was modified
+    DecryptionFailed,
+    /// Reading a file failed
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// The algorithm needs a library liboqs was built without, such as `"OpenSSL"`
+    MissingDependency(&'static str),
+    /// An operation on one item of a batch failed
+    Batch {
+        /// The position of the item in the batch
+        index: usize,
+        /// The error for this item
+        error: alloc::boxed::Box<Error>,
+    },
+    /// A configured algorithm is unknown or cannot be used
+    AlgorithmUnavailable {
+        /// Why the algorithm cannot be used
+        error: alloc::boxed::Box<Error>,
+        /// The enabled algorithms that could be configured instead
+        alternatives: alloc::vec::Vec<kem::Algorithm>,
+    },
+}
+
+impl Error {
+    /// Wrap `error` for the item at `index` of a batch
+    fn batch(index: usize, error: Error) -> Error {
+        Error::Batch {
+            index,
+            error: alloc::boxed::Box::new(error),
+        }
+    }
+
+    /// A stable integer code for the kind of error, for C callers and log pipelines
+    ///
+    /// The codes do not change across minor versions, and new variants get new
+    /// codes. They are independent of the `OQS_RS_*` codes of the C API. The
+    /// fields of a variant are not encoded; [`Error::Batch`] has its own code,
+    /// the code of the failed item is that of its `error`.
+    ///
+    /// | Variant                     | Code |
+    /// |-----------------------------|------|
+    /// | `AlgorithmDisabled`         | 1    |
+    /// | `InvalidLength`             | 2    |
+    /// | `InvalidCiphertextLength`   | 3    |
+    /// | `InvalidSecretKeyLength`    | 4    |
+    /// | `Error`                     | 5    |
+    /// | `ErrorExternalOpenSSL`      | 6    |
+    /// | `OperationNotSupported`     | 7    |
+    /// | `ChecksumMismatch`          | 8    |
+    /// | `InvalidFormat`             | 9    |
+    /// | `KeyReused`                 | 10   |
+    /// | `NotInitialized`            | 11   |
+    /// | `InsufficientShares`        | 12   |
+    /// | `PairwiseConsistencyFailed` | 13   |
+    /// | `Cancelled`                 | 14   |
+    /// | `AlgorithmMismatch`         | 15   |
+    /// | `DecryptionFailed`          | 16   |
+    /// | `Io`                        | 17   |
+    /// | `Batch`                     | 18   |
+    /// | `MissingDependency`         | 19   |
+    /// | `AlgorithmUnavailable`      | 20   |
+    pub fn code(&self) -> i32 {
+        match self {
+            Error::AlgorithmDisabled => 1,
+            Error::InvalidLength { .. } => 2,
+            Error::InvalidCiphertextLength { .. } => 3,
+            Error::InvalidSecretKeyLength { .. } => 4,
+            Error::Error => 5,
+            Error::ErrorExternalOpenSSL => 6,
+            Error::OperationNotSupported => 7,
+            Error::ChecksumMismatch => 8,
+            Error::InvalidFormat => 9,
+            Error::KeyReused => 10,
+            Error::NotInitialized => 11,
+            Error::InsufficientShares { .. } => 12,
+            Error::PairwiseConsistencyFailed => 13,
+            Error::Cancelled => 14,
+            Error::AlgorithmMismatch => 15,
+            Error::DecryptionFailed => 16,
+            #[cfg(feature = "std")]
+            Error::Io(_) => 17,
+            Error::Batch { .. } => 18,
+            Error::MissingDependency(_) => 19,
+            Error::AlgorithmUnavailable { .. } => 20,
+        }
+    }
 }
 #[cfg(feature = "std")]
 impl std::error::Error for Error {}
@@ -108,7 +270,62 @@ impl core::fmt::Display for Error {
         match self {
             Error::AlgorithmDisabled => write!(f, "OQS Error: Algorithm has been disabled"),
             Error::ErrorExternalOpenSSL => write!(f, "OQS error: OpenSSL call failed"),
-            _ => write!(f, "OQS Error!"),
+            Error::InvalidLength { expected, got } => write!(
+                f,
+                "OQS Error: Invalid length: expected {} bytes, got {}",
+                expected, got
+            ),
+            Error::InvalidCiphertextLength { expected, got } => write!(
+                f,
+                "OQS Error: Invalid ciphertext length: expected {} bytes, got {}",
+                expected, got
+            ),
+            Error::InvalidSecretKeyLength { expected, got } => write!(
+                f,
+                "OQS Error: Invalid secret key length: expected {} bytes, got {}",
+                expected, got
+            ),
+            Error::OperationNotSupported => {
+                write!(f, "OQS Error: Operation not supported by this algorithm")
+            }
+            Error::ChecksumMismatch => write!(f, "OQS Error: Checksum mismatch"),
+            Error::InvalidFormat => write!(f, "OQS Error: Invalid format"),
+            Error::KeyReused => write!(f, "OQS Error: Secret key used more than once"),
+            Error::NotInitialized => write!(f, "OQS Error: Algorithm has not been initialized"),
+            Error::InsufficientShares { needed, got } => write!(
+                f,
+                "OQS Error: Insufficient shares: needed {}, got {}",
+                needed, got
+            ),
+            Error::PairwiseConsistencyFailed => {
+                write!(
+                    f,
+                    "OQS Error: Key pair failed the pairwise consistency test"
+                )
+            }
+            Error::Cancelled => write!(f, "OQS Error: Operation cancelled"),
+            Error::DecryptionFailed => write!(f, "OQS Error: Decryption failed"),
+            Error::AlgorithmMismatch => write!(f, "OQS Error: Key belongs to another algorithm"),
+            Error::Error => write!(f, "OQS Error: Operation failed"),
+            #[cfg(feature = "std")]
+            Error::Io(error) => write!(f, "OQS Error: I/O error: {}", error),
+            Error::MissingDependency(library) => write!(
+                f,
+                "OQS Error: Algorithm needs {}, which liboqs was built without",
+                library
+            ),
+            Error::Batch { index, error } => write!(f, "{} (batch item {})", error, index),
+            Error::AlgorithmUnavailable {
+                error,
+                alternatives,
+            } => {
+                write!(f, "{}; enabled alternatives:", error)?;
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    let separator = if i == 0 { " " } else { ", " };
+                    write!(f, "{}{}", separator, alternative.name())?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -122,5 +339,59 @@ fn status_to_result(status: OQS_STATUS) -> Result<()> {
     }
 }
 
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "async")]
+pub mod executor;
+pub mod features;
+pub mod format;
 pub mod kem;
+#[cfg(feature = "kemtls")]
+pub mod kemtls;
+#[cfg(feature = "std")]
+pub mod rand;
 pub mod sig;
+pub mod util;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_error_codes() {
+        let (expected, got) = (32, 31);
+        let table = [
+            (Error::AlgorithmDisabled, 1),
+            (Error::InvalidLength { expected, got }, 2),
+            (Error::InvalidCiphertextLength { expected, got }, 3),
+            (Error::InvalidSecretKeyLength { expected, got }, 4),
+            (Error::Error, 5),
+            (Error::ErrorExternalOpenSSL, 6),
+            (Error::OperationNotSupported, 7),
+            (Error::ChecksumMismatch, 8),
+            (Error::InvalidFormat, 9),
+            (Error::KeyReused, 10),
+            (Error::NotInitialized, 11),
+            (Error::InsufficientShares { needed: 2, got: 1 }, 12),
+            (Error::PairwiseConsistencyFailed, 13),
+            (Error::Cancelled, 14),
+            (Error::AlgorithmMismatch, 15),
+            (Error::DecryptionFailed, 16),
+            (Error::batch(0, Error::Cancelled), 18),
+            (Error::MissingDependency("OpenSSL"), 19),
+            (
+                Error::AlgorithmUnavailable {
+                    error: alloc::boxed::Box::new(Error::InvalidFormat),
+                    alternatives: alloc::vec::Vec::new(),
+                },
+                20,
+            ),
+        ];
+        for (i, (error, code)) in table.iter().enumerate() {
+            assert_eq!(error.code(), *code, "{:?}", error);
+            assert!(table[..i].iter().all(|(other, _)| other.code() != *code));
+        }
+        #[cfg(feature = "std")]
+        assert_eq!(Error::Io(std::io::ErrorKind::NotFound.into()).code(), 17);
+    }
+}
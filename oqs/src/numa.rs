@@ -0,0 +1,53 @@
+//! Placing buffers on a NUMA node through libnuma
+//!
+//! Only supported on Linux; elsewhere, [`has_node`] is false and [`bind`] does nothing.
+
+#[cfg(target_os = "linux")]
+mod ffi {
+    #[link(name = "numa")]
+    extern "C" {
+        pub fn numa_available() -> libc::c_int;
+        pub fn numa_max_node() -> libc::c_int;
+        pub fn numa_tonode_memory(start: *mut libc::c_void, size: usize, node: libc::c_int);
+    }
+}
+
+/// Is `node` a NUMA node of this machine
+#[cfg(target_os = "linux")]
+pub(crate) fn has_node(node: u32) -> bool {
+    // SAFETY: libnuma requires numa_available to be called before any other function
+    unsafe { ffi::numa_available() >= 0 && node as i64 <= ffi::numa_max_node() as i64 }
+}
+
+/// Is `node` a NUMA node of this machine
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn has_node(_node: u32) -> bool {
+    false
+}
+
+/// Place the pages that lie entirely within `buf` on `node`
+///
+/// The policy only applies to pages that have not been touched yet, such as
+/// those of a fresh, large allocation. Pages shared with neighbouring
+/// allocations are left alone, so buffers smaller than two pages stay where they are.
+#[cfg(target_os = "linux")]
+pub(crate) fn bind(buf: &mut [u8], node: u32) {
+    const PAGE_SIZE: usize = 4096;
+    let start = buf.as_mut_ptr() as usize;
+    let aligned_start = (start + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+    let end = (start + buf.len()) & !(PAGE_SIZE - 1);
+    if end > aligned_start {
+        // SAFETY: the range lies within `buf`; only the placement policy of its pages changes
+        unsafe {
+            ffi::numa_tonode_memory(
+                aligned_start as *mut libc::c_void,
+                end - aligned_start,
+                node as libc::c_int,
+            )
+        };
+    }
+}
+
+/// Place the pages that lie entirely within `buf` on `node`
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn bind(_buf: &mut [u8], _node: u32) {}
@@ -0,0 +1,259 @@
+//! Random number generation
+//!
+//! By default liboqs draws randomness from the system RNG.
+//! [`set_rng`] replaces it by a custom generator for the whole process,
+//! [`use_system_rng`] switches back. [`with_rng`] installs a generator
+//! only for the duration of a closure.
+//!
+//! The custom generator is stored behind a global lock, which every call from
+//! liboqs into the generator takes. [`entropy_counter`] counts the bytes drawn
+//! from it. This module requires the `std` feature;
+//! `no_std` users can register a generator directly through
+//! [`ffi::rand`](crate::ffi::rand).
+use alloc::boxed::Box;
+use core::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::ffi::rand as ffi;
+use crate::*;
+
+/// A source of randomness for liboqs
+///
+/// Panicking inside these methods aborts the process,
+/// as they are called from within liboqs.
+pub trait Rng: Send {
+    /// Fill `dest` with random bytes
+    fn fill_bytes(&mut self, dest: &mut [u8]);
+
+    /// Mix `additional_entropy` into the generator state
+    ///
+    /// Called through [`reseed`]. The default implementation ignores the entropy,
+    /// which is only appropriate for generators that reseed themselves.
+    fn reseed(&mut self, additional_entropy: &[u8]) {
+        let _ = additional_entropy;
+    }
+}
+
+/// The custom generator, if one is installed
+static RNG: Mutex<Option<Box<dyn Rng>>> = Mutex::new(None);
+
+/// Bytes drawn from the custom generator, see [`entropy_counter`]
+static ENTROPY_DRAWN: AtomicU64 = AtomicU64::new(0);
+
+fn lock() -> MutexGuard<'static, Option<Box<dyn Rng>>> {
+    // A panicking generator aborts, so the state behind a poisoned lock is still consistent
+    RNG.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Called by liboqs whenever it needs random bytes
+unsafe extern "C" fn custom_randombytes(random_array: *mut u8, bytes_to_read: usize) {
+    let mut rng = lock();
+    match rng.as_mut() {
+        // SAFETY: liboqs passes a buffer of at least `bytes_to_read` bytes
+        Some(rng) => {
+            ENTROPY_DRAWN.fetch_add(bytes_to_read as u64, Ordering::Relaxed);
+            rng.fill_bytes(core::slice::from_raw_parts_mut(random_array, bytes_to_read))
+        }
+        // We were switched back to the system RNG while this call was waiting for the lock
+        None => {
+            drop(rng);
+            ffi::OQS_randombytes(random_array, bytes_to_read)
+        }
+    }
+}
+
+/// Use `rng` for all randomness liboqs needs from now on
+pub fn set_rng(rng: impl Rng + 'static) {
+    set_boxed_rng(Box::new(rng));
+}
+
+/// [`set_rng`] for an already boxed generator
+pub(crate) fn set_boxed_rng(rng: Box<dyn Rng>) {
+    let mut guard = lock();
+    *guard = Some(rng);
+    unsafe { ffi::OQS_randombytes_custom_algorithm(Some(custom_randombytes)) };
+}
+
+/// Switch back to the system RNG, dropping any custom generator
+pub fn use_system_rng() -> Result<()> {
+    let mut guard = lock();
+    let status = unsafe {
+        ffi::OQS_randombytes_switch_algorithm(
+            &ffi::OQS_RAND_alg_system[..] as *const _ as *const i8,
+        )
+    };
+    status_to_result(status)?;
+    *guard = None;
+    Ok(())
+}
+
+/// Restores the generator that was in use before [`with_rng`] when dropped
+struct RestoreRng(Option<Box<dyn Rng>>);
+
+impl Drop for RestoreRng {
+    fn drop(&mut self) {
+        match self.0.take() {
+            Some(previous) => *lock() = Some(previous),
+            // Switching to the system RNG only fails if liboqs was built without it,
+            // in which case the trampoline was never replaced in the first place.
+            None => {
+                let _ = use_system_rng();
+            }
+        }
+    }
+}
+
+/// Use `rng` while running `f`, then restore the previous generator
+///
+/// The previous generator is restored even if `f` panics.
+/// Like [`set_rng`], this affects the whole process and not just the
+/// current thread, so concurrent calls to [`set_rng`] or [`with_rng`]
+/// from other threads will interfere.
+pub fn with_rng<R>(rng: impl Rng + 'static, f: impl FnOnce() -> R) -> R {
+    let previous = {
+        let mut guard = lock();
+        let previous = guard.replace(Box::new(rng));
+        unsafe { ffi::OQS_randombytes_custom_algorithm(Some(custom_randombytes)) };
+        previous
+    };
+    let _restore = RestoreRng(previous);
+    f()
+}
+
+/// Mix `additional_entropy` into the custom generator set through [`set_rng`]
+///
+/// Calling this periodically, for example after a number of operations,
+/// limits what an attacker learns from a single compromise of the
+/// generator state. It is a no-op when the system RNG is in use,
+/// as that is reseeded by the operating system.
+pub fn reseed(additional_entropy: &[u8]) {
+    if let Some(rng) = lock().as_mut() {
+        rng.reseed(additional_entropy);
+    }
+}
+
+/// Number of random bytes liboqs drew from the custom generator since the last reset
+///
+/// Only bytes drawn through a generator installed with [`set_rng`] or [`with_rng`]
+/// are counted; the system RNG is not. To measure an operation, install a
+/// generator, call [`reset_entropy_counter`] and read the counter afterwards.
+/// The counter is global, so operations on other threads are counted as well.
+pub fn entropy_counter() -> u64 {
+    ENTROPY_DRAWN.load(Ordering::Relaxed)
+}
+
+/// Reset [`entropy_counter`] to zero
+pub fn reset_entropy_counter() {
+    ENTROPY_DRAWN.store(0, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    /// Serializes the tests that replace the global generator
+    pub(crate) static RNG_TEST_LOCK: Mutex<()> = Mutex::new(());
+
+    struct CountingRng {
+        state: u8,
+        reseeds: Arc<AtomicUsize>,
+    }
+
+    impl Rng for CountingRng {
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for byte in dest.iter_mut() {
+                self.state = self.state.wrapping_mul(31).wrapping_add(7);
+                *byte = self.state;
+            }
+        }
+
+        fn reseed(&mut self, additional_entropy: &[u8]) {
+            for byte in additional_entropy {
+                self.state ^= byte;
+            }
+            self.reseeds.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_reseed() -> Result<()> {
+        let _lock = RNG_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        crate::init();
+        let reseeds = Arc::new(AtomicUsize::new(0));
+        set_rng(CountingRng {
+            state: 0,
+            reseeds: reseeds.clone(),
+        });
+        reseed(b"fresh entropy");
+        assert_eq!(reseeds.load(Ordering::SeqCst), 1);
+
+        use_system_rng()?;
+        // No-op for the system RNG
+        reseed(b"more entropy");
+        assert_eq!(reseeds.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_entropy_counter() -> Result<()> {
+        let _lock = RNG_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        crate::init();
+        let mut buf = [0u8; 10];
+        set_rng(CountingRng {
+            state: 0,
+            reseeds: Arc::new(AtomicUsize::new(0)),
+        });
+        reset_entropy_counter();
+        unsafe { ffi::OQS_randombytes(buf.as_mut_ptr(), buf.len()) };
+        // Other tests may draw from the generator at the same time
+        assert!(entropy_counter() >= 10);
+
+        use_system_rng()?;
+        reset_entropy_counter();
+        unsafe { ffi::OQS_randombytes(buf.as_mut_ptr(), buf.len()) };
+        assert_eq!(entropy_counter(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_rng_restores() -> Result<()> {
+        let _lock = RNG_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        crate::init();
+        let outer = Arc::new(AtomicUsize::new(0));
+        let inner = Arc::new(AtomicUsize::new(0));
+        set_rng(CountingRng {
+            state: 0,
+            reseeds: outer.clone(),
+        });
+        let inner_rng = CountingRng {
+            state: 0,
+            reseeds: inner.clone(),
+        };
+        let result = std::panic::catch_unwind(|| {
+            with_rng(inner_rng, || {
+                reseed(b"scoped");
+                panic!("closure panics");
+            })
+        });
+        assert!(result.is_err());
+        assert_eq!(inner.load(Ordering::SeqCst), 1);
+
+        // The outer generator is back in place
+        reseed(b"outer");
+        assert_eq!(outer.load(Ordering::SeqCst), 1);
+        assert_eq!(inner.load(Ordering::SeqCst), 1);
+
+        // And the system RNG is restored when it was in use before
+        use_system_rng()?;
+        let inner_rng = CountingRng {
+            state: 0,
+            reseeds: inner.clone(),
+        };
+        assert_eq!(with_rng(inner_rng, || 42), 42);
+        reseed(b"system");
+        assert_eq!(inner.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+}
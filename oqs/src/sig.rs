@@ -385,10 +385,17 @@ impl Sig {
     ) -> Result<()> {
         let signature = signature.into();
         let pk = pk.into();
-        if signature.bytes.len() > self.length_signature()
-            || pk.bytes.len() != self.length_public_key()
-        {
-            return Err(Error::InvalidLength);
+        if signature.bytes.len() > self.length_signature() {
+            return Err(Error::InvalidLength {
+                expected: self.length_signature(),
+                got: signature.bytes.len(),
+            });
+        }
+        if pk.bytes.len() != self.length_public_key() {
+            return Err(Error::InvalidLength {
+                expected: self.length_public_key(),
+                got: pk.bytes.len(),
+            });
         }
         let sig = unsafe { self.sig.as_ref() };
         let func = sig.verify.unwrap();
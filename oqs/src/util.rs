@@ -0,0 +1,196 @@
+//! Helpers for handling byte buffers
+//!
+//! Most helpers are internal; [`ct_select`] is public so protocols built on
+//! this crate can make their own constant-time choices.
+
+use core::sync::atomic::{compiler_fence, Ordering};
+
+/// Overwrite `buf` with zeroes in a way the compiler will not elide.
+pub(crate) fn scrub(buf: &mut [u8]) {
+    for byte in buf.iter_mut() {
+        // SAFETY: `byte` is a valid, aligned reference into `buf`
+        unsafe { core::ptr::write_volatile(byte, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Compare two byte strings in constant time
+///
+/// Every byte of the common prefix is read through a volatile read, so the
+/// comparison cannot be short-circuited. A length difference is folded into
+/// the result instead of returning early; the lengths themselves are public.
+pub(crate) fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    let mut acc = a.len() ^ b.len();
+    for (x, y) in a.iter().zip(b.iter()) {
+        // SAFETY: both are valid references into the input slices
+        let (x, y) = unsafe { (core::ptr::read_volatile(x), core::ptr::read_volatile(y)) };
+        acc |= (x ^ y) as usize;
+    }
+    // SAFETY: `acc` is a valid local
+    unsafe { core::ptr::read_volatile(&acc) == 0 }
+}
+
+/// Write `a` to `out` if `mask` is `0xff`, and `b` if it is `0x00`
+///
+/// Every byte is computed as `(a & mask) | (b & !mask)` with the mask read
+/// through a volatile read, so there is no branch on `mask` for the compiler to
+/// introduce. This is the selection a Fujisaki-Okamoto transform with implicit
+/// rejection needs to return either the shared secret or the rejection value.
+/// Other values of `mask` mix bits of `a` and `b`.
+///
+/// # Panics
+///
+/// If `a`, `b` and `out` do not all have the same length; the lengths are public.
+pub fn ct_select(mask: u8, a: &[u8], b: &[u8], out: &mut [u8]) {
+    assert!(
+        a.len() == out.len() && b.len() == out.len(),
+        "ct_select needs inputs of the output length"
+    );
+    // SAFETY: `mask` is a valid local
+    let mask = unsafe { core::ptr::read_volatile(&mask) };
+    for ((out, &x), &y) in out.iter_mut().zip(a.iter()).zip(b.iter()) {
+        *out = (x & mask) | (y & !mask);
+    }
+}
+
+/// CRC-32 (IEEE 802.3, as used by zlib and PNG) of `data`
+///
+/// Only meant to detect accidental corruption, not tampering.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// 64-bit FNV-1a hash of `data`, to recognize buffers seen before
+///
+/// Not collision resistant against adversarial inputs.
+#[cfg(feature = "std")]
+pub(crate) fn fingerprint(data: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for &byte in data {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// Write `data` as lowercase hex without separators
+#[cfg(feature = "hex")]
+pub(crate) fn write_hex(f: &mut core::fmt::Formatter<'_>, data: &[u8]) -> core::fmt::Result {
+    for byte in data {
+        write!(f, "{:02x}", byte)?;
+    }
+    Ok(())
+}
+
+/// Scrubs the wrapped buffer when dropped, also when unwinding
+pub(crate) struct ScrubGuard<'a>(pub(crate) &'a mut [u8]);
+
+impl Drop for ScrubGuard<'_> {
+    fn drop(&mut self) {
+        scrub(self.0);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Small xorshift generator, good enough to produce test inputs
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_ct_eq_matches_naive() {
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        let mut a = [0u8; 48];
+        let mut b = [0u8; 48];
+        for _ in 0..10_000 {
+            let len_a = (rng.next() % 48) as usize;
+            let len_b = if rng.next() & 1 == 0 {
+                len_a
+            } else {
+                (rng.next() % 48) as usize
+            };
+            for byte in a.iter_mut() {
+                *byte = rng.next() as u8;
+            }
+            // Make equal inputs likely, with the occasional flipped byte
+            b.copy_from_slice(&a);
+            if len_b > 0 && rng.next() & 1 == 0 {
+                b[(rng.next() as usize) % len_b] ^= 1 << (rng.next() % 8);
+            }
+            let (a, b) = (&a[..len_a], &b[..len_b]);
+            assert_eq!(ct_eq(a, b), a == b, "mismatch for {:?} and {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_ct_eq_lengths() {
+        assert!(ct_eq(&[], &[]));
+        assert!(!ct_eq(&[0], &[]));
+        assert!(!ct_eq(&[1, 2, 3], &[1, 2]));
+        assert!(!ct_eq(&[1, 2], &[1, 2, 3]));
+    }
+
+    #[test]
+    fn test_ct_select() {
+        let a = [1u8, 2, 3, 0xff];
+        let b = [0xa0u8, 0, 0x55, 7];
+        let mut out = [0u8; 4];
+        ct_select(0xff, &a, &b, &mut out);
+        assert_eq!(out, a);
+        ct_select(0x00, &a, &b, &mut out);
+        assert_eq!(out, b);
+        ct_select(0x0f, &a, &b, &mut out);
+        assert_eq!(out, [0xa1, 2, 0x53, 0x0f]);
+        ct_select(0xff, &[], &[], &mut []);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_ct_select_length_mismatch() {
+        ct_select(0xff, &[1, 2], &[3], &mut [0, 0]);
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414f_a339
+        );
+    }
+
+    #[test]
+    fn test_scrub() {
+        let mut buf = [0xffu8; 64];
+        scrub(&mut buf);
+        assert!(buf.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_scrub_guard() {
+        let mut buf = [0xffu8; 64];
+        {
+            let guard = ScrubGuard(&mut buf);
+            assert!(guard.0.iter().all(|&b| b == 0xff));
+        }
+        assert!(buf.iter().all(|&b| b == 0));
+    }
+}
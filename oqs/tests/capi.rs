@@ -0,0 +1,162 @@
+//! Use the C interface through its exported symbols, as C code would
+#![cfg(feature = "capi")]
+use std::os::raw::{c_char, c_int};
+
+use oqs::capi::{
+    OQS_RS_ALGORITHM_DISABLED, OQS_RS_BUFFER_TOO_SMALL, OQS_RS_INVALID_LENGTH, OQS_RS_OK,
+};
+use oqs::kem;
+
+extern "C" {
+    fn oqs_rs_kem_lengths(
+        alg: *const c_char,
+        pk_len: *mut usize,
+        sk_len: *mut usize,
+        ct_len: *mut usize,
+        ss_len: *mut usize,
+    ) -> c_int;
+    fn oqs_rs_kem_keypair(
+        alg: *const c_char,
+        pk_out: *mut u8,
+        pk_len: *mut usize,
+        sk_out: *mut u8,
+        sk_len: *mut usize,
+    ) -> c_int;
+    fn oqs_rs_kem_encapsulate(
+        alg: *const c_char,
+        pk: *const u8,
+        pk_len: usize,
+        ct_out: *mut u8,
+        ct_len: *mut usize,
+        ss_out: *mut u8,
+        ss_len: *mut usize,
+    ) -> c_int;
+    fn oqs_rs_kem_decapsulate(
+        alg: *const c_char,
+        sk: *const u8,
+        sk_len: usize,
+        ct: *const u8,
+        ct_len: usize,
+        ss_out: *mut u8,
+        ss_len: *mut usize,
+    ) -> c_int;
+}
+
+#[test]
+fn test_round_trip() {
+    for alg in kem::all()
+        .iter()
+        .filter(|alg| alg.is_enabled() && !alg.is_nike())
+    {
+        let name = std::ffi::CString::new(alg.name()).unwrap();
+        let name = name.as_ptr();
+        let (mut pk_len, mut sk_len, mut ct_len, mut ss_len) = (0, 0, 0, 0);
+        unsafe {
+            assert_eq!(
+                oqs_rs_kem_lengths(name, &mut pk_len, &mut sk_len, &mut ct_len, &mut ss_len),
+                OQS_RS_OK
+            );
+            let mut pk = vec![0; pk_len];
+            let mut sk = vec![0; sk_len];
+            assert_eq!(
+                oqs_rs_kem_keypair(
+                    name,
+                    pk.as_mut_ptr(),
+                    &mut pk_len,
+                    sk.as_mut_ptr(),
+                    &mut sk_len
+                ),
+                OQS_RS_OK
+            );
+            assert_eq!((pk_len, sk_len), (pk.len(), sk.len()));
+
+            let mut ct = vec![0; ct_len];
+            let mut ss = vec![0; ss_len];
+            assert_eq!(
+                oqs_rs_kem_encapsulate(
+                    name,
+                    pk.as_ptr(),
+                    pk.len(),
+                    ct.as_mut_ptr(),
+                    &mut ct_len,
+                    ss.as_mut_ptr(),
+                    &mut ss_len
+                ),
+                OQS_RS_OK
+            );
+            let mut ss2 = vec![0; ss_len];
+            assert_eq!(
+                oqs_rs_kem_decapsulate(
+                    name,
+                    sk.as_ptr(),
+                    sk.len(),
+                    ct.as_ptr(),
+                    ct.len(),
+                    ss2.as_mut_ptr(),
+                    &mut ss_len
+                ),
+                OQS_RS_OK
+            );
+            assert_eq!(ss, ss2, "{}", alg);
+        }
+    }
+}
+
+#[test]
+fn test_errors() {
+    let (mut pk_len, mut sk_len, mut ct_len, mut ss_len) = (0, 0, 0, 0);
+    let unknown = b"NoSuchKem\0".as_ptr() as *const c_char;
+    unsafe {
+        assert_eq!(
+            oqs_rs_kem_lengths(unknown, &mut pk_len, &mut sk_len, &mut ct_len, &mut ss_len),
+            OQS_RS_ALGORITHM_DISABLED
+        );
+    }
+
+    let alg = match kem::all()
+        .iter()
+        .find(|alg| alg.is_enabled() && !alg.is_nike())
+    {
+        Some(alg) => alg,
+        None => return,
+    };
+    let name = std::ffi::CString::new(alg.name()).unwrap();
+    let name = name.as_ptr();
+    unsafe {
+        assert_eq!(
+            oqs_rs_kem_lengths(name, &mut pk_len, &mut sk_len, &mut ct_len, &mut ss_len),
+            OQS_RS_OK
+        );
+        // A short buffer reports the length it needs
+        let required = pk_len;
+        let mut pk = vec![0; required];
+        let mut sk = vec![0; sk_len];
+        pk_len = required - 1;
+        assert_eq!(
+            oqs_rs_kem_keypair(
+                name,
+                pk.as_mut_ptr(),
+                &mut pk_len,
+                sk.as_mut_ptr(),
+                &mut sk_len
+            ),
+            OQS_RS_BUFFER_TOO_SMALL
+        );
+        assert_eq!(pk_len, required);
+
+        let mut ct = vec![0; ct_len];
+        let mut ss = vec![0; ss_len];
+        assert_eq!(
+            oqs_rs_kem_encapsulate(
+                name,
+                pk.as_ptr(),
+                pk.len() - 1,
+                ct.as_mut_ptr(),
+                &mut ct_len,
+                ss.as_mut_ptr(),
+                &mut ss_len
+            ),
+            OQS_RS_INVALID_LENGTH
+        );
+    }
+}
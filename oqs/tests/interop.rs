@@ -0,0 +1,125 @@
+//! Decapsulate known-answer vectors produced by other implementations
+//!
+//! See `tests/kats/README.md` for the expected files.
+use std::fs;
+use std::path::Path;
+
+use oqs::kem::{self, Kem};
+
+/// A single entry of a `.rsp` file
+#[derive(Default)]
+struct Vector {
+    count: String,
+    pk: Vec<u8>,
+    sk: Vec<u8>,
+    ct: Vec<u8>,
+    ss: Vec<u8>,
+}
+
+fn from_hex(hex: &str) -> Vec<u8> {
+    assert!(hex.len() & 1 == 0, "odd number of hex digits");
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("invalid hex digit"))
+        .collect()
+}
+
+/// Parse the entries of a `.rsp` file, ignoring fields other than count, pk, sk, ct and ss
+fn parse_rsp(contents: &str) -> Vec<Vector> {
+    let mut vectors = Vec::new();
+    let mut current: Option<Vector> = None;
+    for line in contents.lines().map(str::trim) {
+        let (key, value) = match line.find('=') {
+            Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+            None => continue,
+        };
+        if key == "count" {
+            vectors.extend(current.take());
+            current = Some(Vector {
+                count: value.to_string(),
+                ..Vector::default()
+            });
+            continue;
+        }
+        let vector = current.as_mut().expect("field before the first count");
+        match key {
+            "pk" => vector.pk = from_hex(value),
+            "sk" => vector.sk = from_hex(value),
+            "ct" => vector.ct = from_hex(value),
+            "ss" => vector.ss = from_hex(value),
+            _ => {}
+        }
+    }
+    vectors.extend(current);
+    vectors
+}
+
+#[test]
+fn test_hex() {
+    assert_eq!(from_hex("00ff10"), vec![0x00, 0xff, 0x10]);
+    assert_eq!(from_hex("ABcd"), vec![0xab, 0xcd]);
+}
+
+#[test]
+fn test_parse_rsp() {
+    let vectors = parse_rsp(
+        "# comment\n\ncount = 0\nseed = 00\npk = 06\nsk = 0102\nct = 03\nss = 04\n\ncount = 1\nsk = 05\n",
+    );
+    assert_eq!(vectors.len(), 2);
+    assert_eq!(vectors[0].count, "0");
+    assert_eq!(vectors[0].pk, vec![6]);
+    assert_eq!(vectors[0].sk, vec![1, 2]);
+    assert_eq!(vectors[0].ct, vec![3]);
+    assert_eq!(vectors[0].ss, vec![4]);
+    assert_eq!(vectors[1].sk, vec![5]);
+}
+
+#[test]
+fn test_known_answers() {
+    oqs::init();
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/kats");
+    let mut checked = 0;
+    for entry in fs::read_dir(&dir).expect("tests/kats is missing") {
+        let path = entry.unwrap().path();
+        if path.extension() != Some("rsp".as_ref()) {
+            continue;
+        }
+        let name = path.file_stem().unwrap().to_str().unwrap();
+        let algorithm = kem::all()
+            .iter()
+            .copied()
+            .find(|alg| alg.name() == name)
+            .unwrap_or_else(|| panic!("{} does not name a KEM", path.display()));
+        let kem = match Kem::new(algorithm) {
+            Ok(kem) => kem,
+            // The algorithm is not compiled into this build
+            Err(oqs::Error::AlgorithmDisabled | oqs::Error::MissingDependency(_)) => continue,
+            Err(e) => panic!("{}: {}", name, e),
+        };
+        kem.init().unwrap();
+        for vector in parse_rsp(&fs::read_to_string(&path).unwrap()) {
+            let sk = kem
+                .secret_key_from_bytes(&vector.sk)
+                .expect("wrong sk length");
+            let ct = kem
+                .ciphertext_from_bytes(&vector.ct)
+                .expect("wrong ct length");
+            let ss = kem.decapsulate(sk, ct).unwrap();
+            assert_eq!(
+                ss.as_ref(),
+                &vector.ss[..],
+                "{} count = {}",
+                name,
+                vector.count
+            );
+            // The key pair also works with ciphertexts of this implementation
+            let pk = kem
+                .public_key_from_bytes(&vector.pk)
+                .expect("wrong pk length");
+            let (ct, ss) = kem.encapsulate(pk).unwrap();
+            assert_eq!(kem.decapsulate(sk, &ct).unwrap(), ss);
+            checked += 1;
+        }
+    }
+    assert!(checked > 0, "no known-answer vectors in {}", dir.display());
+}